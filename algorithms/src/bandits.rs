@@ -0,0 +1,326 @@
+use environments::bandit::KArmedBandit;
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::exploration;
+
+pub trait BanditAgent {
+    fn select_action(&mut self, rng: &mut Xoshiro256PlusPlus) -> usize;
+    fn update(&mut self, action: usize, reward: f32);
+    /// Forgets everything learned, before a new independent run.
+    fn reset(&mut self);
+}
+
+/// ε-greedy action values, with sample averages or a constant step size.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EpsilonGreedy {
+    values: Vec<f32>,
+    counts: Vec<usize>,
+    epsilon: f32,
+    alpha: Option<f32>,
+    initial_value: f32,
+}
+
+impl EpsilonGreedy {
+    pub fn new(num_actions: usize, epsilon: f32) -> Self {
+        EpsilonGreedy {
+            values: vec![0.0; num_actions],
+            counts: vec![0; num_actions],
+            epsilon,
+            alpha: None,
+            initial_value: 0.0,
+        }
+    }
+
+    /// Greedy agent with optimistic initial values and a constant step size (section 2.6).
+    pub fn optimistic(num_actions: usize, initial_value: f32, alpha: f32) -> Self {
+        EpsilonGreedy {
+            values: vec![initial_value; num_actions],
+            counts: vec![0; num_actions],
+            epsilon: 0.0,
+            alpha: Some(alpha),
+            initial_value,
+        }
+    }
+
+    pub fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+impl BanditAgent for EpsilonGreedy {
+    fn select_action(&mut self, rng: &mut Xoshiro256PlusPlus) -> usize {
+        let actions: Vec<usize> = (0..self.values.len()).collect();
+        exploration::epsilon_greedy(&self.values, &actions, self.epsilon, rng)
+    }
+
+    fn update(&mut self, action: usize, reward: f32) {
+        self.counts[action] += 1;
+        let step_size = self.alpha.unwrap_or(1.0 / self.counts[action] as f32);
+        self.values[action] += step_size * (reward - self.values[action]);
+    }
+
+    fn reset(&mut self) {
+        self.values.iter_mut().for_each(|v| *v = self.initial_value);
+        self.counts.iter_mut().for_each(|n| *n = 0);
+    }
+}
+
+/// Upper-confidence-bound action selection (section 2.7).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UCB1 {
+    values: Vec<f32>,
+    counts: Vec<usize>,
+    c: f32,
+    t: usize,
+}
+
+impl UCB1 {
+    pub fn new(num_actions: usize, c: f32) -> Self {
+        UCB1 {
+            values: vec![0.0; num_actions],
+            counts: vec![0; num_actions],
+            c,
+            t: 0,
+        }
+    }
+}
+
+impl BanditAgent for UCB1 {
+    fn select_action(&mut self, _rng: &mut Xoshiro256PlusPlus) -> usize {
+        let actions: Vec<usize> = (0..self.values.len()).collect();
+        exploration::ucb1(&self.values, &self.counts, self.t, self.c, &actions)
+    }
+
+    fn update(&mut self, action: usize, reward: f32) {
+        self.t += 1;
+        self.counts[action] += 1;
+        self.values[action] += (reward - self.values[action]) / self.counts[action] as f32;
+    }
+
+    fn reset(&mut self) {
+        self.values.iter_mut().for_each(|v| *v = 0.0);
+        self.counts.iter_mut().for_each(|n| *n = 0);
+        self.t = 0;
+    }
+}
+
+/// Gradient bandit with softmax preferences and an optional average-reward baseline (section 2.8).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GradientBandit {
+    preferences: Vec<f32>,
+    alpha: f32,
+    use_baseline: bool,
+    average_reward: f32,
+    t: usize,
+}
+
+impl GradientBandit {
+    pub fn new(num_actions: usize, alpha: f32, use_baseline: bool) -> Self {
+        GradientBandit {
+            preferences: vec![0.0; num_actions],
+            alpha,
+            use_baseline,
+            average_reward: 0.0,
+            t: 0,
+        }
+    }
+
+    fn probabilities(&self) -> Vec<f32> {
+        let actions: Vec<usize> = (0..self.preferences.len()).collect();
+        exploration::softmax(&self.preferences, &actions, 1.0)
+    }
+}
+
+impl BanditAgent for GradientBandit {
+    fn select_action(&mut self, rng: &mut Xoshiro256PlusPlus) -> usize {
+        let actions: Vec<usize> = (0..self.preferences.len()).collect();
+        exploration::sample_from_probabilities(&self.probabilities(), &actions, rng)
+    }
+
+    fn update(&mut self, action: usize, reward: f32) {
+        self.t += 1;
+        let baseline = if self.use_baseline { self.average_reward } else { 0.0 };
+        let probs = self.probabilities();
+
+        for (a, (preference, p)) in self.preferences.iter_mut().zip(probs.iter()).enumerate() {
+            let indicator = if a == action { 1.0 } else { 0.0 };
+            *preference += self.alpha * (reward - baseline) * (indicator - p);
+        }
+
+        self.average_reward += (reward - self.average_reward) / self.t as f32;
+    }
+
+    fn reset(&mut self) {
+        self.preferences.iter_mut().for_each(|h| *h = 0.0);
+        self.average_reward = 0.0;
+        self.t = 0;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Posterior {
+    /// Beta(alpha, beta) per arm, for rewards in {0, 1}.
+    Beta { alphas: Vec<f32>, betas: Vec<f32> },
+    /// Normal posterior over the mean of unit-variance Gaussian rewards, N(0, 1) prior.
+    Gaussian { sums: Vec<f32>, counts: Vec<usize> },
+}
+
+/// Thompson sampling with a conjugate posterior per arm.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThompsonSampling {
+    posterior: Posterior,
+}
+
+impl ThompsonSampling {
+    pub fn bernoulli(num_actions: usize) -> Self {
+        ThompsonSampling {
+            posterior: Posterior::Beta {
+                alphas: vec![1.0; num_actions],
+                betas: vec![1.0; num_actions],
+            },
+        }
+    }
+
+    pub fn gaussian(num_actions: usize) -> Self {
+        ThompsonSampling {
+            posterior: Posterior::Gaussian {
+                sums: vec![0.0; num_actions],
+                counts: vec![0; num_actions],
+            },
+        }
+    }
+}
+
+impl BanditAgent for ThompsonSampling {
+    fn select_action(&mut self, rng: &mut Xoshiro256PlusPlus) -> usize {
+        match &self.posterior {
+            Posterior::Beta { alphas, betas } => {
+                let actions: Vec<usize> = (0..alphas.len()).collect();
+                exploration::thompson_beta(alphas, betas, &actions, rng)
+            }
+            Posterior::Gaussian { sums, counts } => {
+                let actions: Vec<usize> = (0..sums.len()).collect();
+                // With a N(0, 1) prior and unit noise the posterior precision is n + 1
+                let means: Vec<f32> = sums.iter().zip(counts.iter()).map(|(&s, &n)| s / (n as f32 + 1.0)).collect();
+                let std_devs: Vec<f32> = counts.iter().map(|&n| 1.0 / (n as f32 + 1.0).sqrt()).collect();
+                exploration::thompson_normal(&means, &std_devs, &actions, rng)
+            }
+        }
+    }
+
+    fn update(&mut self, action: usize, reward: f32) {
+        match &mut self.posterior {
+            Posterior::Beta { alphas, betas } => {
+                alphas[action] += reward;
+                betas[action] += 1.0 - reward;
+            }
+            Posterior::Gaussian { sums, counts } => {
+                sums[action] += reward;
+                counts[action] += 1;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        match &mut self.posterior {
+            Posterior::Beta { alphas, betas } => {
+                alphas.iter_mut().for_each(|a| *a = 1.0);
+                betas.iter_mut().for_each(|b| *b = 1.0);
+            }
+            Posterior::Gaussian { sums, counts } => {
+                sums.iter_mut().for_each(|s| *s = 0.0);
+                counts.iter_mut().for_each(|n| *n = 0);
+            }
+        }
+    }
+}
+
+/// Average learning curves over independent runs of the testbed.
+pub struct BanditReport {
+    /// Mean reward at each step.
+    pub average_reward: Vec<f32>,
+    /// Percentage of runs that chose the optimal arm at each step.
+    pub optimal_action: Vec<f32>,
+}
+
+impl BanditReport {
+    pub fn display(&self, log_interval: usize) {
+        let steps = self.average_reward.len();
+        for i in (0..steps).step_by(log_interval.max(1)) {
+            let end = (i + log_interval).min(steps);
+            let len = (end - i) as f32;
+            let reward: f32 = self.average_reward[i..end].iter().sum::<f32>() / len;
+            let optimal: f32 = self.optimal_action[i..end].iter().sum::<f32>() / len;
+            println!("Steps {}-{}: Avg Reward: {:.3}, Optimal action: {:.1}%", i, end - 1, reward, optimal);
+        }
+    }
+}
+
+/// Runs `runs` independent bandit problems for `steps` pulls each. The bandit
+/// of run `i` is built with `make_bandit(i)` and the agent is reset between runs.
+pub fn run_testbed<A: BanditAgent>(
+    agent: &mut A,
+    make_bandit: impl Fn(u64) -> KArmedBandit,
+    runs: usize,
+    steps: usize,
+) -> BanditReport {
+    let mut average_reward = vec![0.0; steps];
+    let mut optimal_action = vec![0.0; steps];
+
+    for run in 0..runs {
+        let mut bandit = make_bandit(run as u64);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(run as u64);
+        agent.reset();
+
+        for step in 0..steps {
+            let optimal = bandit.optimal_action();
+            let action = agent.select_action(&mut rng);
+            let reward = bandit.pull(action);
+            agent.update(action, reward);
+
+            average_reward[step] += reward;
+            if action == optimal {
+                optimal_action[step] += 1.0;
+            }
+        }
+    }
+
+    let runs = runs.max(1) as f32;
+    BanditReport {
+        average_reward: average_reward.iter().map(|r| r / runs).collect(),
+        optimal_action: optimal_action.iter().map(|o| 100.0 * o / runs).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::bandit::ArmDistribution;
+    use environments::Environment;
+
+    fn final_optimal<A: BanditAgent>(agent: &mut A, distribution: ArmDistribution) -> f32 {
+        let report = run_testbed(agent, |seed| KArmedBandit::with_config(10, distribution, 0.0, seed), 200, 500);
+        report.optimal_action[400..].iter().sum::<f32>() / 100.0
+    }
+
+    #[test]
+    fn test_agents_beat_random_choice() {
+        assert!(final_optimal(&mut EpsilonGreedy::new(10, 0.1), ArmDistribution::Gaussian) > 50.0);
+        assert!(final_optimal(&mut EpsilonGreedy::optimistic(10, 5.0, 0.1), ArmDistribution::Gaussian) > 50.0);
+        assert!(final_optimal(&mut UCB1::new(10, 2.0), ArmDistribution::Gaussian) > 50.0);
+        assert!(final_optimal(&mut GradientBandit::new(10, 0.1, true), ArmDistribution::Gaussian) > 50.0);
+        assert!(final_optimal(&mut ThompsonSampling::gaussian(10), ArmDistribution::Gaussian) > 50.0);
+        assert!(final_optimal(&mut ThompsonSampling::bernoulli(10), ArmDistribution::Bernoulli) > 50.0);
+    }
+
+    #[test]
+    fn test_bandit_is_single_step_environment() {
+        let mut bandit = KArmedBandit::new();
+        assert_eq!(bandit.available_actions().len(), 10);
+        bandit.step(bandit.optimal_action()).unwrap();
+        assert!(bandit.is_game_over());
+        bandit.reset();
+        assert!(!bandit.is_game_over());
+    }
+}
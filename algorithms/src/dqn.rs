@@ -1,20 +1,29 @@
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256PlusPlus;
-use environments::Environment;
+use environments::{Environment, EnvError};
+use environments::vec_env::VecEnv;
 use crate::RLAlgorithm;
+use crate::exploration::epsilon_greedy;
+use crate::features::{FeatureExtractor, LinearQ};
 use serde::{Serialize, Deserialize};
 
-#[derive(Clone, Serialize, Deserialize)]
+// States are kept as ids and raw observations, which are empty for one-hot
+// features, and turned into features when a minibatch is sampled
+#[derive(Clone)]
 struct Transition {
     state: usize,
+    observation: Vec<f32>,
     action: usize,
     reward: f32,
     next_state: usize,
+    next_observation: Vec<f32>,
     done: bool,
 }
 
+// Only the capacity is saved: the transitions are training data, not part of the model
 #[derive(Clone, Serialize, Deserialize)]
 struct ReplayMemory {
+    #[serde(skip)]
     transitions: Vec<Transition>,
     capacity: usize,
 }
@@ -51,7 +60,7 @@ impl ReplayMemory {
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DQN {
-    weights: Vec<Vec<f32>>,
+    q: LinearQ,
     epsilon: f32,
     alpha: f32,
     gamma: f32,
@@ -69,18 +78,33 @@ impl DQN {
         memory_capacity: usize,
         batch_size: usize,
     ) -> Self {
-        let mut rng = rand::thread_rng();
+        Self::with_features(
+            FeatureExtractor::OneHot { num_states },
+            num_actions,
+            alpha,
+            epsilon,
+            gamma,
+            memory_capacity,
+            batch_size,
+        )
+    }
+
+    /// DQN whose "network" is linear in the features of `extractor`.
+    pub fn with_features(
+        extractor: FeatureExtractor,
+        num_actions: usize,
+        alpha: f32,
+        epsilon: f32,
+        gamma: f32,
+        memory_capacity: usize,
+        batch_size: usize,
+    ) -> Self {
+        let mut rng = rand::rng();
         // Inicializa la "red" con pesos aleatorios pequeños.
-        let weights = (0..num_states)
-            .map(|_| {
-                (0..num_actions)
-                    .map(|_| rng.gen_range(-0.1..0.1))
-                    .collect::<Vec<f32>>()
-            })
-            .collect::<Vec<Vec<f32>>>();
+        let q = LinearQ::new(extractor, num_actions, || rng.random_range(-0.1..0.1));
 
         Self {
-            weights,
+            q,
             alpha,
             epsilon,
             gamma,
@@ -88,86 +112,165 @@ impl DQN {
             batch_size,
         }
     }
+
+    /// Collects experience from every environment of `envs` in lockstep and
+    /// learns from one minibatch per transition, as `train` does. Returns the
+    /// reward of the first `max_episodes` episodes to finish.
+    pub fn train_vec<T: Environment>(&mut self, envs: &mut VecEnv<T>, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut rewards_per_episode = Vec::with_capacity(max_episodes);
+
+        envs.reset();
+        let mut states = self.observe_all(envs);
+        while rewards_per_episode.len() < max_episodes {
+            let actions: Vec<usize> = envs.available_actions().iter()
+                .zip(&states)
+                .map(|(available, (state, observation))| {
+                    let values = self.q.values(&self.q.features(*state, observation));
+                    epsilon_greedy(&values, available, self.epsilon, &mut rng)
+                })
+                .collect();
+
+            let batch = envs.step(&actions)?;
+            // Finished environments have already been reset: terminated ones never use their
+            // next state, truncated ones bootstrap from the state they were cut off in
+            let next_states = self.observe_all(envs);
+            for i in 0..envs.num_envs() {
+                let (next_state, next_observation) = match &batch.final_states[i] {
+                    Some((state, observation)) => {
+                        self.q.remember(*state, observation);
+                        (*state, observation.clone())
+                    }
+                    None => next_states[i].clone(),
+                };
+                self.memory.push(Transition {
+                    state: states[i].0,
+                    observation: states[i].1.clone(),
+                    action: actions[i],
+                    reward: batch.rewards[i],
+                    next_state,
+                    next_observation,
+                    done: batch.dones[i] && !batch.truncated[i],
+                });
+                self.learn(&mut rng);
+            }
+            rewards_per_episode.extend(batch.episode_returns.iter().flatten());
+            states = next_states;
+        }
+        rewards_per_episode.truncate(max_episodes);
+        Ok(rewards_per_episode)
+    }
+
+    // State id and observation of every environment, remembered for `get_best_action`
+    fn observe_all<T: Environment>(&mut self, envs: &VecEnv<T>) -> Vec<(usize, Vec<f32>)> {
+        let observations = if self.q.reads_observations() {
+            envs.observations()
+        } else {
+            vec![Vec::new(); envs.num_envs()]
+        };
+        envs.states().into_iter()
+            .zip(observations)
+            .map(|(state, observation)| {
+                self.q.remember(state, &observation);
+                (state, observation)
+            })
+            .collect()
+    }
+
+    // Si hay suficientes transiciones, se muestrea un minibatch y se actualiza la red.
+    fn learn(&mut self, rng: &mut Xoshiro256PlusPlus) {
+        if self.memory.len() < self.batch_size {
+            return;
+        }
+        let minibatch = self.memory.sample(self.batch_size, rng);
+        for transition in minibatch {
+            let features = self.q.features(transition.state, &transition.observation);
+            // Predicción actual
+            let q_current = self.q.value(&features, transition.action);
+            // Calcula el valor máximo del siguiente estado (0 si es terminal).
+            let max_q_next = if transition.done {
+                0.0
+            } else {
+                self.q.max_value(&self.q.features(transition.next_state, &transition.next_observation))
+            };
+            let target = transition.reward + self.gamma * max_q_next;
+            let error = target - q_current;
+            // Actualización de los pesos para la acción tomada.
+            self.q.update(&features, transition.action, self.alpha * error);
+        }
+    }
+
+    /// Number of state ids the model knows, or `None` when it learned from
+    /// observations and only knows the states it remembers one for.
+    pub fn num_states(&self) -> Option<usize> {
+        self.q.num_states()
+    }
+
+    /// Whether `get_best_action` can answer for `state`.
+    pub fn knows_state(&self, state: usize) -> bool {
+        self.q.knows_state(state)
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.q.num_actions()
+    }
 }
 
 impl RLAlgorithm for DQN {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
         let mut rewards_per_episode = Vec::with_capacity(max_episodes);
 
         for _ in 0..max_episodes {
             env.reset();
             let mut total_reward = 0.0;
-            let mut s = env.state_id();
+            let mut state = env.state_id();
+            let mut observation = self.q.observation_of(env);
+            self.q.remember(state, &observation);
 
             while !env.is_game_over() {
                 let available = env.available_actions();
                 // Selección de acción: ε‑greedy.
-                let a = if rng.gen::<f32>() < self.epsilon {
-                    *available.choose(&mut rng).unwrap()
-                } else {
-                    self.get_best_action(s, &available)
-                };
+                let values = self.q.values(&self.q.features(state, &observation));
+                let a = epsilon_greedy(&values, &available, self.epsilon, &mut rng);
 
                 // Se utiliza el mismo metodo que en Q‑Learning para calcular la recompensa.
                 let prev_score = env.score();
-                env.step(a);
+                env.step(a)?;
                 let r = env.score() - prev_score;
                 total_reward += r;
-                let s_next = env.state_id();
-                let done = env.is_game_over();
+                let next_state = env.state_id();
+                let next_observation = self.q.observation_of(env);
+                self.q.remember(next_state, &next_observation);
+                let done = env.terminated();
 
                 // Almacena la transición en el replay memory.
                 self.memory.push(Transition {
-                    state: s,
+                    state: std::mem::replace(&mut state, next_state),
+                    observation: std::mem::replace(&mut observation, next_observation.clone()),
                     action: a,
                     reward: r,
-                    next_state: s_next,
+                    next_state,
+                    next_observation,
                     done,
                 });
 
-                s = s_next;
-
-                // Si hay suficientes transiciones, se muestrea un minibatch y se actualiza la red.
-                if self.memory.len() >= self.batch_size {
-                    let minibatch = self.memory.sample(self.batch_size, &mut rng);
-                    for transition in minibatch {
-                        // Predicción actual
-                        let q_current = self.weights[transition.state][transition.action];
-                        // Calcula el valor máximo del siguiente estado (0 si es terminal).
-                        let max_q_next = if transition.done {
-                            0.0
-                        } else {
-                            *self.weights[transition.next_state]
-                                .iter()
-                                .max_by(|a, b| a.partial_cmp(b).unwrap())
-                                .unwrap()
-                        };
-                        let target = transition.reward + self.gamma * max_q_next;
-                        let error = target - q_current;
-                        // Actualización del peso para la acción tomada.
-                        self.weights[transition.state][transition.action] += self.alpha * error;
-                    }
-                }
+                self.learn(&mut rng);
             }
             rewards_per_episode.push(total_reward);
         }
-        rewards_per_episode
+        Ok(rewards_per_episode)
     }
 
     /// Durante la evaluación, devuelve la acción con mayor Q-valor para el estado dado,
     /// restringido a las acciones disponibles.
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
-        let mut best_action = available_actions[0];
-        let mut best_value = self.weights[state][best_action];
-        for &a in available_actions.iter().skip(1) {
-            let value = self.weights[state][a];
-            if value > best_value {
-                best_value = value;
-                best_action = a;
-            }
-        }
-        best_action
+        self.q.best_action(&self.q.features_of_state(state), available_actions)
+    }
+
+    fn best_action_in<T: Environment>(&self, env: &T) -> usize {
+        let features = self.q.features(env.state_id(), &self.q.observation_of(env));
+        self.q.best_action(&features, &env.available_actions())
     }
 }
 
@@ -175,6 +278,8 @@ impl RLAlgorithm for DQN {
 mod tests {
     use super::*;
     use environments::line_world::LineWorld;
+    use environments::cart_pole::{self, CartPole};
+    use environments::wrappers::TimeLimit;
     use crate::RLAlgorithm;
 
     #[test]
@@ -182,10 +287,8 @@ mod tests {
         let env = LineWorld::new();
         // Por ejemplo: memoria con capacidad 1000 y batch_size de 32.
         let dqn = DQN::new(env.num_states(), env.num_actions(), 0.1, 0.1, 0.99, 1000, 32);
-        assert_eq!(dqn.weights.len(), env.num_states());
-        for row in dqn.weights.iter() {
-            assert_eq!(row.len(), env.num_actions());
-        }
+        assert_eq!(dqn.q.num_features(), env.num_states());
+        assert_eq!(dqn.q.num_actions(), env.num_actions());
         // El replay memory se inicializa vacío.
         assert_eq!(dqn.memory.len(), 0);
     }
@@ -194,8 +297,75 @@ mod tests {
     fn test_dqn_training() {
         let mut env = LineWorld::new();
         let mut dqn = DQN::new(env.num_states(), env.num_actions(), 0.1, 1.0, 0.99, 1000, 32);
-        let rewards = dqn.train(&mut env, 100);
+        let rewards = dqn.train(&mut env, 100).unwrap();
         // Se deben generar 100 episodios.
         assert_eq!(rewards.len(), 100);
+
+        // Neither the replay memory nor observations are saved
+        let json = serde_json::to_string(&dqn).unwrap();
+        assert!(!json.contains("transitions"));
+        assert!(!json.contains("observations"));
+        let loaded: DQN = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.memory.len(), 0);
+        assert_eq!(loaded.get_best_action(2, &[0, 1]), dqn.get_best_action(2, &[0, 1]));
+    }
+
+    #[test]
+    fn test_dqn_learns_from_vectorised_environments() {
+        let mut envs = VecEnv::new(&LineWorld::new(), 4);
+        let mut dqn = DQN::new(envs.num_states(), envs.num_actions(), 0.1, 0.2, 0.99, 1000, 32);
+        let rewards = dqn.train_vec(&mut envs, 200).unwrap();
+        assert_eq!(rewards.len(), 200);
+        assert_eq!(dqn.get_best_action(2, &[0, 1]), 1);
+        assert_eq!(dqn.get_best_action(1, &[0, 1]), 1);
+    }
+
+    #[test]
+    fn test_dqn_on_polynomial_features() {
+        let mut env = LineWorld::new();
+        let extractor = FeatureExtractor::Polynomial { dims: 1, degree: 2 };
+        let mut dqn = DQN::with_features(extractor, env.num_actions(), 0.01, 0.2, 0.9, 1000, 16);
+        let rewards = dqn.train(&mut env, 200).unwrap();
+        assert_eq!(rewards.len(), 200);
+        // Line World starts in the middle and wins on the right
+        env.reset();
+        assert_eq!(dqn.best_action_in(&env), 1);
+    }
+
+    #[test]
+    fn test_dqn_runs_on_cart_pole_observations() {
+        let mut env = CartPole::with_seed(1);
+        let extractor = FeatureExtractor::Polynomial { dims: 4, degree: 1 };
+        let mut dqn = DQN::with_features(extractor, env.num_actions(), 0.01, 0.1, 0.99, 1000, 16);
+        let rewards = dqn.train(&mut env, 20).unwrap();
+        assert_eq!(rewards.len(), 20);
+        // One point per step the pole stayed up
+        assert!(rewards.iter().all(|&r| r >= 1.0 && r <= cart_pole::MAX_STEPS as f32), "{:?}", rewards);
+
+        env.reset();
+        while !env.is_game_over() {
+            env.step(dqn.best_action_in(&env)).unwrap();
+        }
+        assert!(env.score() >= 1.0);
+    }
+
+    #[test]
+    fn test_time_limit_cut_offs_are_not_terminal_transitions() {
+        let mut env = TimeLimit::with_max_steps(LineWorld::new(), 1);
+        let mut dqn = DQN::new(env.num_states(), env.num_actions(), 0.1, 0.1, 0.99, 1000, 32);
+        dqn.train(&mut env, 20).unwrap();
+        // One step from the middle never reaches an end of the line
+        assert_eq!(dqn.memory.len(), 20);
+        assert!(dqn.memory.transitions.iter().all(|t| !t.done));
+    }
+
+    #[test]
+    fn test_batched_cut_offs_bootstrap_from_their_last_state() {
+        let mut envs = VecEnv::new(&TimeLimit::with_max_steps(LineWorld::new(), 1), 2);
+        let mut dqn = DQN::new(5, 2, 0.1, 0.1, 0.99, 1000, 32);
+        dqn.train_vec(&mut envs, 20).unwrap();
+        assert!(dqn.memory.transitions.iter().all(|t| !t.done));
+        // The next state is where the step led, not the middle the environment was reset to
+        assert!(dqn.memory.transitions.iter().all(|t| t.next_state == 1 || t.next_state == 3));
     }
 }
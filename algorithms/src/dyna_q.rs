@@ -1,16 +1,19 @@
-use environments::Environment;
+use environments::{Environment, EnvError};
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256PlusPlus;
 use crate::RLAlgorithm;
+use crate::exploration::epsilon_greedy;
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 
+// (state, action) -> (reward, next_state)
+type Model = HashMap<(usize, usize), (f32, usize)>;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DynaQ {
     q_table: Vec<Vec<f32>>,
     #[serde(serialize_with = "serialize_model", deserialize_with = "deserialize_model")]
-    model: HashMap<(usize, usize), (f32, usize)>, // (state, action) -> (reward, next_state)
+    model: Model,
     alpha: f32,
     epsilon: f32,
     gamma: f32,
@@ -18,7 +21,7 @@ pub struct DynaQ {
 }
 
 fn serialize_model<S>(
-    model: &HashMap<(usize, usize), (f32, usize)>, // Changed this line
+    model: &Model,
     serializer: S
 ) -> Result<S::Ok, S::Error>
 where
@@ -33,7 +36,7 @@ where
 
 fn deserialize_model<'de, D>(
     deserializer: D
-) -> Result<HashMap<(usize, usize), (f32, usize)>, D::Error> // Changed this line
+) -> Result<Model, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -104,7 +107,7 @@ impl DynaQ {
 }
 
 impl RLAlgorithm for DynaQ {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
         let mut episode_rewards = Vec::new();
 
@@ -117,15 +120,11 @@ impl RLAlgorithm for DynaQ {
                 let available_actions = env.available_actions();
 
                 // Choose action using epsilon-greedy policy
-                let action = if rand::random::<f32>() <= self.epsilon {
-                    *available_actions.choose(&mut rng).unwrap()
-                } else {
-                    self.get_best_action(state, &available_actions)
-                };
+                let action = epsilon_greedy(&self.q_table[state], &available_actions, self.epsilon, &mut rng);
 
                 // Take action in environment
                 let prev_score = env.score();
-                env.step(action);
+                env.step(action)?;
                 let reward = env.score() - prev_score;
                 total_reward += reward;
                 let next_state = env.state_id();
@@ -151,7 +150,7 @@ impl RLAlgorithm for DynaQ {
             episode_rewards.push(total_reward);
         }
 
-        episode_rewards
+        Ok(episode_rewards)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -0,0 +1,152 @@
+//! Action-selection rules shared by the bandit agents and the full RL algorithms.
+//!
+//! Every helper works on a slice of per-action values (one row of a Q-table)
+//! and only considers the given `available_actions`.
+
+use rand::prelude::*;
+use rand_distr::{Beta, Distribution, Normal};
+
+/// Action with the highest value, ties going to the first one.
+pub fn greedy(values: &[f32], available_actions: &[usize]) -> usize {
+    let mut best_action = available_actions[0];
+    let mut best_value = values[best_action];
+
+    for &action in available_actions.iter().skip(1) {
+        if values[action] > best_value {
+            best_action = action;
+            best_value = values[action];
+        }
+    }
+
+    best_action
+}
+
+/// Action with the highest value, ties broken uniformly at random.
+pub fn greedy_random_tie<R: Rng>(values: &[f32], available_actions: &[usize], rng: &mut R) -> usize {
+    let best_value = available_actions.iter()
+        .map(|&a| values[a])
+        .fold(f32::NEG_INFINITY, f32::max);
+    let best: Vec<usize> = available_actions.iter()
+        .copied()
+        .filter(|&a| values[a] == best_value)
+        .collect();
+    *best.choose(rng).unwrap_or(&available_actions[0])
+}
+
+pub fn epsilon_greedy<R: Rng>(values: &[f32], available_actions: &[usize], epsilon: f32, rng: &mut R) -> usize {
+    if rng.random::<f32>() < epsilon {
+        *available_actions.choose(rng).unwrap()
+    } else {
+        greedy_random_tie(values, available_actions, rng)
+    }
+}
+
+/// Probability that `epsilon_greedy` picks `action`, the greedy share being
+/// split between the actions tied for the best value.
+pub fn epsilon_greedy_probability(values: &[f32], available_actions: &[usize], epsilon: f32, action: usize) -> f32 {
+    let explore = epsilon / available_actions.len() as f32;
+    let best_value = available_actions.iter()
+        .map(|&a| values[a])
+        .fold(f32::NEG_INFINITY, f32::max);
+    if values[action] == best_value {
+        let ties = available_actions.iter().filter(|&&a| values[a] == best_value).count();
+        (1.0 - epsilon) / ties as f32 + explore
+    } else {
+        explore
+    }
+}
+
+/// UCB1: `Q(a) + c * sqrt(ln t / N(a))`. Untried actions are always picked first.
+pub fn ucb1(values: &[f32], counts: &[usize], total_count: usize, c: f32, available_actions: &[usize]) -> usize {
+    if let Some(&untried) = available_actions.iter().find(|&&a| counts[a] == 0) {
+        return untried;
+    }
+
+    let ln_t = (total_count.max(1) as f32).ln();
+    let scores: Vec<f32> = (0..values.len())
+        .map(|a| {
+            if counts[a] == 0 {
+                f32::NEG_INFINITY
+            } else {
+                values[a] + c * (ln_t / counts[a] as f32).sqrt()
+            }
+        })
+        .collect();
+    greedy(&scores, available_actions)
+}
+
+/// Softmax (Boltzmann) distribution over the available actions, indexed like `preferences`.
+pub fn softmax(preferences: &[f32], available_actions: &[usize], temperature: f32) -> Vec<f32> {
+    let mut probs = vec![0.0; preferences.len()];
+    let max_pref = available_actions.iter()
+        .map(|&a| preferences[a])
+        .fold(f32::NEG_INFINITY, f32::max);
+
+    let mut sum = 0.0;
+    for &a in available_actions {
+        probs[a] = ((preferences[a] - max_pref) / temperature).exp();
+        sum += probs[a];
+    }
+    for &a in available_actions {
+        probs[a] /= sum;
+    }
+
+    probs
+}
+
+pub fn sample_from_probabilities<R: Rng>(probs: &[f32], available_actions: &[usize], rng: &mut R) -> usize {
+    let r: f32 = rng.random();
+    let mut cumulative = 0.0;
+    for &a in available_actions {
+        cumulative += probs[a];
+        if r < cumulative {
+            return a;
+        }
+    }
+    *available_actions.last().unwrap()
+}
+
+/// Thompson sampling with Beta(alpha, beta) posteriors, for rewards in {0, 1}.
+pub fn thompson_beta<R: Rng>(alphas: &[f32], betas: &[f32], available_actions: &[usize], rng: &mut R) -> usize {
+    let samples: Vec<f32> = alphas.iter()
+        .zip(betas.iter())
+        .map(|(&a, &b)| Beta::new(a, b).map(|d| d.sample(rng)).unwrap_or(0.0))
+        .collect();
+    greedy(&samples, available_actions)
+}
+
+/// Thompson sampling with Normal(mean, std_dev) posteriors.
+pub fn thompson_normal<R: Rng>(means: &[f32], std_devs: &[f32], available_actions: &[usize], rng: &mut R) -> usize {
+    let samples: Vec<f32> = means.iter()
+        .zip(std_devs.iter())
+        .map(|(&m, &s)| Normal::new(m, s).map(|d| d.sample(rng)).unwrap_or(m))
+        .collect();
+    greedy(&samples, available_actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_epsilon_greedy_probability_matches_sampling_with_ties() {
+        let values = [1.0, 0.0, 1.0, 0.5];
+        let available_actions = [0, 1, 2];
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        let mut counts = [0; 4];
+        for _ in 0..40_000 {
+            counts[epsilon_greedy(&values, &available_actions, 0.3, &mut rng)] += 1;
+        }
+
+        let total: f32 = available_actions.iter()
+            .map(|&a| epsilon_greedy_probability(&values, &available_actions, 0.3, a))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-6);
+        for a in available_actions {
+            let expected = epsilon_greedy_probability(&values, &available_actions, 0.3, a);
+            assert!((counts[a] as f32 / 40_000.0 - expected).abs() < 0.01, "action {}", a);
+        }
+        assert_eq!(counts[3], 0);
+    }
+}
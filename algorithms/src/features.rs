@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use environments::Environment;
+use crate::tile_coding::TileCoder;
+
+/// Turns a state into the feature vector of a linear approximator.
+///
+/// `OneHot` only looks at the state id and reproduces a table. The others
+/// read `Environment::observation`, so nearby states share features.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FeatureExtractor {
+    /// One feature per state id.
+    OneHot { num_states: usize },
+    /// Every product of the `dims` observation components with each exponent
+    /// between 0 and `degree`, the constant term included.
+    Polynomial { dims: usize, degree: usize },
+    /// Gaussian radial basis functions of width `width` around `centers`.
+    Rbf { centers: Vec<Vec<f32>>, width: f32 },
+    /// Binary features of a `TileCoder`.
+    TileCoding(TileCoder),
+}
+
+impl FeatureExtractor {
+    /// RBFs centred on a regular grid of `per_dim` points per dimension between `low` and `high`.
+    pub fn rbf_grid(low: &[f32], high: &[f32], per_dim: usize, width: f32) -> Self {
+        assert!(per_dim > 1, "An RBF grid needs at least two centres per dimension");
+        let centers = (0..per_dim.pow(low.len() as u32))
+            .map(|mut index| {
+                low.iter().zip(high).map(|(&l, &h)| {
+                    let i = index % per_dim;
+                    index /= per_dim;
+                    l + (h - l) * i as f32 / (per_dim - 1) as f32
+                }).collect()
+            })
+            .collect();
+        FeatureExtractor::Rbf { centers, width }
+    }
+
+    pub fn num_features(&self) -> usize {
+        match self {
+            FeatureExtractor::OneHot { num_states } => *num_states,
+            FeatureExtractor::Polynomial { dims, degree } => (degree + 1).pow(*dims as u32),
+            FeatureExtractor::Rbf { centers, .. } => centers.len(),
+            FeatureExtractor::TileCoding(coder) => coder.num_features(),
+        }
+    }
+
+    pub fn features(&self, state: usize, observation: &[f32]) -> Vec<f32> {
+        match self {
+            FeatureExtractor::OneHot { num_states } => {
+                let mut features = vec![0.0; *num_states];
+                features[state] = 1.0;
+                features
+            }
+            FeatureExtractor::Polynomial { dims, degree } => {
+                assert_eq!(
+                    observation.len(), *dims,
+                    "Polynomial features over {} dimensions got an observation of length {}", dims, observation.len()
+                );
+                (0..self.num_features())
+                    .map(|mut exponents| {
+                        observation[..*dims].iter().fold(1.0, |product, &x| {
+                            let exponent = exponents % (degree + 1);
+                            exponents /= degree + 1;
+                            product * x.powi(exponent as i32)
+                        })
+                    })
+                    .collect()
+            }
+            FeatureExtractor::Rbf { centers, width } => {
+                centers.iter()
+                    .map(|center| {
+                        let distance: f32 = center.iter().zip(observation).map(|(c, x)| (x - c).powi(2)).sum();
+                        (-distance / (2.0 * width * width)).exp()
+                    })
+                    .collect()
+            }
+            FeatureExtractor::TileCoding(coder) => {
+                let mut features = vec![0.0; coder.num_features()];
+                for tile in coder.active_tiles(observation) {
+                    features[tile] = 1.0;
+                }
+                features
+            }
+        }
+    }
+}
+
+/// Linear action values: one weight vector per action over the features of
+/// a `FeatureExtractor`.
+///
+/// `RLAlgorithm::get_best_action` only receives a state id, so the
+/// observation last seen in each state is kept to answer it after training.
+/// `OneHot` features only need the state id, so nothing is kept for them.
+/// Observations are not saved: a loaded model that reads them only answers
+/// `best_action_in`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LinearQ {
+    extractor: FeatureExtractor,
+    weights: Vec<Vec<f32>>,
+    #[serde(skip)]
+    observations: HashMap<usize, Vec<f32>>,
+}
+
+impl LinearQ {
+    pub fn new(extractor: FeatureExtractor, num_actions: usize, mut init: impl FnMut() -> f32) -> Self {
+        let weights = (0..num_actions)
+            .map(|_| (0..extractor.num_features()).map(|_| init()).collect())
+            .collect();
+        LinearQ { extractor, weights, observations: HashMap::new() }
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.extractor.num_features()
+    }
+
+    /// Number of state ids for `OneHot` features. Other extractors only
+    /// know the states they remember an observation for, and return `None`.
+    pub fn num_states(&self) -> Option<usize> {
+        match self.extractor {
+            FeatureExtractor::OneHot { num_states } => Some(num_states),
+            _ => None,
+        }
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn features(&self, state: usize, observation: &[f32]) -> Vec<f32> {
+        self.extractor.features(state, observation)
+    }
+
+    /// Whether the features depend on observations rather than on state ids only.
+    pub fn reads_observations(&self) -> bool {
+        !matches!(self.extractor, FeatureExtractor::OneHot { .. })
+    }
+
+    /// Observation of the current state of `env` as the extractor reads it:
+    /// empty for `OneHot`, which would otherwise build a vector as long as
+    /// the state space on every step.
+    pub fn observation_of<T: Environment>(&self, env: &T) -> Vec<f32> {
+        if self.reads_observations() {
+            env.observation()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Remembers the observation of a state seen in an environment for `features_of_state`.
+    pub fn remember(&mut self, state: usize, observation: &[f32]) {
+        if self.reads_observations() {
+            self.observations.insert(state, observation.to_vec());
+        }
+    }
+
+    /// Features of a state seen in an environment, remembered for `features_of_state`.
+    pub fn observe(&mut self, state: usize, observation: Vec<f32>) -> Vec<f32> {
+        self.remember(state, &observation);
+        self.extractor.features(state, &observation)
+    }
+
+    /// Whether `features_of_state` has something to go on for `state`.
+    pub fn knows_state(&self, state: usize) -> bool {
+        match self.extractor {
+            FeatureExtractor::OneHot { num_states } => state < num_states,
+            _ => self.observations.contains_key(&state),
+        }
+    }
+
+    /// Features of a state id, from the observation last seen in it. Panics
+    /// for a state without one, as every state of a loaded model is.
+    pub fn features_of_state(&self, state: usize) -> Vec<f32> {
+        match (&self.extractor, self.observations.get(&state)) {
+            (FeatureExtractor::OneHot { .. }, _) => self.extractor.features(state, &[]),
+            (_, Some(observation)) => self.extractor.features(state, observation),
+            (_, None) => panic!(
+                "No observation of state {} to choose from: models on observation features need best_action_in",
+                state
+            ),
+        }
+    }
+
+    pub fn value(&self, features: &[f32], action: usize) -> f32 {
+        features.iter().zip(&self.weights[action]).map(|(f, w)| f * w).sum()
+    }
+
+    /// Moves the weights of `action` by `step` along the features, the
+    /// gradient of a linear value.
+    pub fn update(&mut self, features: &[f32], action: usize, step: f32) {
+        for (w, f) in self.weights[action].iter_mut().zip(features) {
+            *w += step * f;
+        }
+    }
+
+    /// Moves every weight by `step` times its entry in `traces`, laid out
+    /// like the weights as `[action][feature]`.
+    pub fn update_traced(&mut self, traces: &[Vec<f32>], step: f32) {
+        for (weights, traces) in self.weights.iter_mut().zip(traces) {
+            for (w, z) in weights.iter_mut().zip(traces) {
+                *w += step * z;
+            }
+        }
+    }
+
+    /// Value of every action, indexed by action.
+    pub fn values(&self, features: &[f32]) -> Vec<f32> {
+        (0..self.num_actions()).map(|a| self.value(features, a)).collect()
+    }
+
+    pub fn max_value(&self, features: &[f32]) -> f32 {
+        (0..self.num_actions())
+            .map(|a| self.value(features, a))
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    pub fn best_action(&self, features: &[f32], available_actions: &[usize]) -> usize {
+        let mut best_action = available_actions[0];
+        let mut best_value = self.value(features, best_action);
+        for &action in available_actions.iter().skip(1) {
+            let value = self.value(features, action);
+            if value > best_value {
+                best_action = action;
+                best_value = value;
+            }
+        }
+        best_action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extractor_sizes_and_values() {
+        let polynomial = FeatureExtractor::Polynomial { dims: 2, degree: 2 };
+        assert_eq!(polynomial.num_features(), 9);
+        // Exponents (0,0), (1,0), (2,0), (0,1), ...
+        assert_eq!(&polynomial.features(0, &[2.0, 3.0])[..4], &[1.0, 2.0, 4.0, 3.0]);
+
+        let rbf = FeatureExtractor::rbf_grid(&[0.0], &[1.0], 3, 0.5);
+        assert_eq!(rbf.num_features(), 3);
+        let features = rbf.features(0, &[0.5]);
+        assert_eq!(features[1], 1.0);
+        assert!((features[0] - features[2]).abs() < 1e-6);
+
+        let one_hot = FeatureExtractor::OneHot { num_states: 4 };
+        assert_eq!(one_hot.features(2, &[]), vec![0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "need best_action_in")]
+    fn test_unknown_states_have_no_features() {
+        let mut q = LinearQ::new(FeatureExtractor::Polynomial { dims: 1, degree: 1 }, 2, || 1.0);
+        q.observe(3, vec![2.0]);
+        assert_eq!(q.features_of_state(3), vec![1.0, 2.0]);
+        q.features_of_state(4);
+    }
+
+    #[test]
+    fn test_observations_are_not_saved() {
+        let mut q = LinearQ::new(FeatureExtractor::Polynomial { dims: 1, degree: 1 }, 2, || 1.0);
+        q.observe(3, vec![2.0]);
+        let loaded: LinearQ = serde_json::from_str(&serde_json::to_string(&q).unwrap()).unwrap();
+        assert!(loaded.observations.is_empty());
+        assert!(!loaded.knows_state(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "got an observation of length 4")]
+    fn test_polynomial_features_check_the_observation_length() {
+        FeatureExtractor::Polynomial { dims: 2, degree: 1 }.features(0, &[0.0; 4]);
+    }
+
+    #[test]
+    fn test_one_hot_keeps_no_observations() {
+        let mut q = LinearQ::new(FeatureExtractor::OneHot { num_states: 3 }, 2, || 0.0);
+        assert_eq!(q.observe(1, Vec::new()), vec![0.0, 1.0, 0.0]);
+        assert!(q.observations.is_empty());
+        assert_eq!(q.features_of_state(2), vec![0.0, 0.0, 1.0]);
+    }
+}
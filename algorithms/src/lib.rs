@@ -8,8 +8,111 @@ pub mod sarsa;
 pub mod reinforce;
 pub mod semi_gradient_sarsa;
 pub mod dqn;
+pub mod td_zero;
+pub mod n_step_td;
+pub mod td_lambda;
+pub mod mc_prediction;
+pub mod exploration;
+pub mod bandits;
+pub mod trained_ai;
+pub mod features;
+pub mod tile_coding;
+pub mod sarsa_lambda;
+pub mod q_lambda;
+pub mod n_step_sarsa;
+
+use environments::EnvError;
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
 
 pub trait RLAlgorithm: Send {
-    fn train<T: environments::Environment + Clone>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32>;
+    /// Trains for `max_episodes` episodes and returns the reward of each one.
+    /// An environment error stops training and is returned to the caller;
+    /// everything learned up to that step is kept.
+    fn train<T: environments::Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError>;
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize;
-}
\ No newline at end of file
+
+    /// Best action in the current state of `env`. Learners working on
+    /// observations rather than state ids override it.
+    fn best_action_in<T: environments::Environment>(&self, env: &T) -> usize {
+        self.get_best_action(env.state_id(), &env.available_actions())
+    }
+}
+
+/// Estimates the state values of a fixed policy.
+///
+/// Policies are tables of action probabilities indexed by `[state][action]`;
+/// probabilities are renormalised over the available actions of each state.
+pub trait PredictionAlgorithm {
+    /// Runs one episode following `policy` and updates the value estimates.
+    /// Returns the undiscounted episode return.
+    fn run_episode<T: environments::Environment>(
+        &mut self,
+        env: &mut T,
+        policy: &[Vec<f32>],
+        rng: &mut Xoshiro256PlusPlus,
+    ) -> Result<f32, EnvError>;
+
+    fn get_values(&self) -> &[f32];
+
+    /// Runs `max_episodes` episodes and returns the RMS error against
+    /// `true_values` after each of them. `true_values` may be shorter than the
+    /// value table, in which case only its first states are compared.
+    fn evaluate<T: environments::Environment>(
+        &mut self,
+        env: &mut T,
+        policy: &[Vec<f32>],
+        max_episodes: usize,
+        true_values: &[f32],
+    ) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut errors = Vec::with_capacity(max_episodes);
+
+        for _ in 0..max_episodes {
+            self.run_episode(env, policy, &mut rng)?;
+            errors.push(rms_error(self.get_values(), true_values));
+        }
+
+        Ok(errors)
+    }
+}
+
+/// Root mean squared error between the first `true_values.len()` estimates and the true values.
+pub fn rms_error(values: &[f32], true_values: &[f32]) -> f32 {
+    if true_values.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = values.iter()
+        .zip(true_values.iter())
+        .map(|(v, t)| (v - t).powi(2))
+        .sum();
+    (sum / true_values.len() as f32).sqrt()
+}
+
+/// The equiprobable random policy.
+pub fn uniform_policy(num_states: usize, num_actions: usize) -> Vec<Vec<f32>> {
+    vec![vec![1.0 / num_actions as f32; num_actions]; num_states]
+}
+
+// Samples an action from the policy restricted to the available actions
+pub(crate) fn sample_policy_action(
+    policy: &[Vec<f32>],
+    state: usize,
+    available_actions: &[usize],
+    rng: &mut Xoshiro256PlusPlus,
+) -> usize {
+    let total: f32 = available_actions.iter().map(|&a| policy[state][a]).sum();
+    if total <= 0.0 {
+        return *available_actions.choose(rng).unwrap();
+    }
+
+    let r = rng.random::<f32>() * total;
+    let mut cumulative = 0.0;
+    for &action in available_actions {
+        cumulative += policy[state][action];
+        if r < cumulative {
+            return action;
+        }
+    }
+    *available_actions.last().unwrap()
+}
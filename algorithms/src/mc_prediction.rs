@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use environments::{Environment, EnvError};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::{PredictionAlgorithm, sample_policy_action};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VisitMode {
+    FirstVisit,
+    EveryVisit,
+}
+
+/// Constant-α Monte Carlo prediction.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MonteCarloPrediction {
+    values: Vec<f32>,
+    alpha: f32,
+    gamma: f32,
+    mode: VisitMode,
+}
+
+impl MonteCarloPrediction {
+    pub fn new(num_states: usize, alpha: f32, gamma: f32, mode: VisitMode) -> Self {
+        MonteCarloPrediction {
+            values: vec![0.0; num_states],
+            alpha,
+            gamma,
+            mode,
+        }
+    }
+}
+
+impl PredictionAlgorithm for MonteCarloPrediction {
+    fn run_episode<T: Environment>(&mut self, env: &mut T, policy: &[Vec<f32>], rng: &mut Xoshiro256PlusPlus) -> Result<f32, EnvError> {
+        env.reset();
+        let mut episode = Vec::new();
+
+        while !env.is_game_over() {
+            let state = env.state_id();
+            let action = sample_policy_action(policy, state, &env.available_actions(), rng);
+
+            let prev_score = env.score();
+            env.step(action)?;
+            episode.push((state, env.score() - prev_score));
+        }
+
+        // Returns are computed backwards; for first-visit only the earliest
+        // occurrence of a state in the episode is used
+        let mut returns = vec![0.0; episode.len()];
+        let mut g = 0.0;
+        for (t, &(_, reward)) in episode.iter().enumerate().rev() {
+            g = self.gamma * g + reward;
+            returns[t] = g;
+        }
+
+        let mut visited = HashSet::new();
+        for (&(state, _), &g) in episode.iter().zip(returns.iter()) {
+            if self.mode == VisitMode::FirstVisit && !visited.insert(state) {
+                continue;
+            }
+            self.values[state] += self.alpha * (g - self.values[state]);
+        }
+
+        Ok(episode.iter().map(|&(_, r)| r).sum())
+    }
+
+    fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::random_walk::RandomWalk;
+    use crate::uniform_policy;
+
+    #[test]
+    fn test_every_visit_updates_revisited_states_again() {
+        let mut env = RandomWalk::with_size(5);
+        let policy = uniform_policy(env.num_states(), env.num_actions());
+        let mut first = MonteCarloPrediction::new(env.num_states(), 0.5, 1.0, VisitMode::FirstVisit);
+        let mut every = MonteCarloPrediction::new(env.num_states(), 0.5, 1.0, VisitMode::EveryVisit);
+        first.evaluate(&mut env, &policy, 1, &[]).unwrap();
+        every.evaluate(&mut env, &policy, 1, &[]).unwrap();
+
+        // Every return of the episode is its final reward g. First-visit
+        // moves each visited state halfway to g once, every-visit once per
+        // visit, so states the walk came back to end up closer to g.
+        let first = first.get_values();
+        let every = every.get_values();
+        assert!(first.iter().zip(every).all(|(f, e)| f.abs() <= e.abs()));
+        assert!(first.iter().zip(every).any(|(f, e)| f.abs() < e.abs()));
+        assert!(first.iter().all(|f| [0.0, 0.5].contains(&f.abs())));
+    }
+}
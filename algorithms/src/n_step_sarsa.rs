@@ -0,0 +1,277 @@
+use environments::{Environment, EnvError};
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::RLAlgorithm;
+use crate::exploration::{epsilon_greedy, epsilon_greedy_probability, greedy};
+
+/// What the n-step return bootstraps from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NStepBackup {
+    /// The action actually taken n steps later (Sutton & Barto, section 7.2).
+    Sarsa,
+    /// The expected value under the ε-greedy policy n steps later (section 7.2).
+    ExpectedSarsa,
+    /// Learns the greedy policy off-policy, weighting every step by the
+    /// probability the greedy policy gives to the action taken instead of
+    /// by importance sampling (section 7.5).
+    TreeBackup,
+}
+
+/// n-step Sarsa and its expected and tree-backup variants, on a Q-table laid
+/// out like the one of `Sarsa`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NStepSarsa {
+    q_table: Vec<Vec<f32>>,
+    n: usize,
+    alpha: f32,
+    epsilon: f32,
+    gamma: f32,
+    backup: NStepBackup,
+}
+
+impl NStepSarsa {
+    pub fn new(
+        num_states: usize,
+        num_actions: usize,
+        n: usize,
+        alpha: f32,
+        epsilon: f32,
+        gamma: f32,
+        backup: NStepBackup,
+    ) -> Self {
+        assert!(n > 0, "n must be at least 1");
+        NStepSarsa {
+            q_table: vec![vec![0.0; num_actions]; num_states],
+            n,
+            alpha,
+            epsilon,
+            gamma,
+            backup,
+        }
+    }
+
+    pub fn get_q_table(&self) -> &Vec<Vec<f32>> {
+        &self.q_table
+    }
+
+    // Probability of each available action under the policy the backup evaluates
+    fn target_probability(&self, state: usize, available_actions: &[usize], action: usize) -> f32 {
+        let epsilon = match self.backup {
+            NStepBackup::TreeBackup => 0.0,
+            _ => self.epsilon,
+        };
+        epsilon_greedy_probability(&self.q_table[state], available_actions, epsilon, action)
+    }
+
+    fn expected_value(&self, state: usize, available_actions: &[usize]) -> f32 {
+        available_actions.iter()
+            .map(|&a| self.target_probability(state, available_actions, a) * self.q_table[state][a])
+            .sum()
+    }
+
+    // Return used to update the pair visited at time tau. `steps[t]` holds
+    // S_t, A_t and the actions available in S_t, `rewards[t]` is R_{t+1}, and
+    // `end` is the episode length T once known. A truncated episode also
+    // has `steps[T]`, which the returns reaching T bootstrap from.
+    fn n_step_return(
+        &self,
+        steps: &[(usize, usize, Vec<usize>)],
+        rewards: &[f32],
+        tau: usize,
+        end: Option<usize>,
+        truncated: bool,
+    ) -> f32 {
+        let horizon = tau + self.n;
+        let last = end.map_or(horizon, |end| horizon.min(end));
+        let bootstraps = truncated || end.is_none_or(|end| horizon < end);
+
+        match self.backup {
+            NStepBackup::Sarsa | NStepBackup::ExpectedSarsa => {
+                let mut g = 0.0;
+                let mut discount = 1.0;
+                for &reward in &rewards[tau..last] {
+                    g += discount * reward;
+                    discount *= self.gamma;
+                }
+                if bootstraps {
+                    let (state, action, ref available_actions) = steps[last];
+                    g += discount * match self.backup {
+                        NStepBackup::Sarsa => self.q_table[state][action],
+                        _ => self.expected_value(state, available_actions),
+                    };
+                }
+                g
+            }
+            NStepBackup::TreeBackup => {
+                // Built backwards from the last step, each earlier step adding
+                // the values of the actions it did not take
+                let mut g = if bootstraps {
+                    let (state, _, ref available_actions) = steps[last];
+                    rewards[last - 1] + self.gamma * self.expected_value(state, available_actions)
+                } else {
+                    rewards[last - 1]
+                };
+                for k in (tau + 1..last).rev() {
+                    let (state, action, ref available_actions) = steps[k];
+                    let others: f32 = available_actions.iter()
+                        .filter(|&&a| a != action)
+                        .map(|&a| self.target_probability(state, available_actions, a) * self.q_table[state][a])
+                        .sum();
+                    let taken = self.target_probability(state, available_actions, action);
+                    g = rewards[k - 1] + self.gamma * (others + taken * g);
+                }
+                g
+            }
+        }
+    }
+}
+
+impl RLAlgorithm for NStepSarsa {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut episode_rewards = Vec::new();
+
+        for _ in 0..max_episodes {
+            env.reset();
+            let mut total_reward = 0.0;
+
+            let available_actions = env.available_actions();
+            if available_actions.is_empty() {
+                continue;
+            }
+            let state = env.state_id();
+            let action = epsilon_greedy(&self.q_table[state], &available_actions, self.epsilon, &mut rng);
+            let mut steps = vec![(state, action, available_actions)];
+            let mut rewards = Vec::new();
+            let mut end = None;
+            let mut truncated = false;
+
+            for t in 0.. {
+                if end.is_none() {
+                    let prev_score = env.score();
+                    env.step(steps[t].1)?;
+                    let reward = env.score() - prev_score;
+                    total_reward += reward;
+                    rewards.push(reward);
+
+                    let next_available_actions = env.available_actions();
+                    if env.terminated() || next_available_actions.is_empty() {
+                        end = Some(t + 1);
+                    } else {
+                        // A truncated episode ends here too, but its last pair is still worth bootstrapping from
+                        let next_state = env.state_id();
+                        let next_action = epsilon_greedy(&self.q_table[next_state], &next_available_actions, self.epsilon, &mut rng);
+                        steps.push((next_state, next_action, next_available_actions));
+                        if env.is_game_over() {
+                            end = Some(t + 1);
+                            truncated = true;
+                        }
+                    }
+                }
+
+                // The pair visited n - 1 steps ago now has all it needs. Once
+                // the episode is over, t keeps going without acting until
+                // the last pair is updated.
+                if t + 1 >= self.n {
+                    let tau = t + 1 - self.n;
+                    let g = self.n_step_return(&steps, &rewards, tau, end, truncated);
+                    let (state, action, _) = steps[tau];
+                    self.q_table[state][action] += self.alpha * (g - self.q_table[state][action]);
+                    if end == Some(tau + 1) {
+                        break;
+                    }
+                }
+            }
+
+            episode_rewards.push(total_reward);
+        }
+
+        Ok(episode_rewards)
+    }
+
+    fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
+        greedy(&self.q_table[state], available_actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::line_world::LineWorld;
+    use environments::grid_world::GridWorld;
+    use environments::random_walk::RandomWalk;
+    use environments::wrappers::TimeLimit;
+    use crate::rms_error;
+
+    // RMS error of the random policy's state values on the 19-state walk,
+    // read from the expected Sarsa Q-table
+    fn random_walk_error(n: usize, episodes: usize) -> f32 {
+        let mut env = RandomWalk::new();
+        let mut sarsa = NStepSarsa::new(env.num_states(), env.num_actions(), n, 0.4, 1.0, 1.0, NStepBackup::ExpectedSarsa);
+        sarsa.train(&mut env, episodes).unwrap();
+        let values: Vec<f32> = sarsa.get_q_table().iter()
+            .map(|q| q.iter().sum::<f32>() / q.len() as f32)
+            .collect();
+        rms_error(&values, &env.true_values())
+    }
+
+    #[test]
+    fn test_long_returns_reach_the_start_state_in_one_episode() {
+        for backup in [NStepBackup::Sarsa, NStepBackup::ExpectedSarsa, NStepBackup::TreeBackup] {
+            let mut env = LineWorld::new();
+            let mut sarsa = NStepSarsa::new(env.num_states(), env.num_actions(), 4, 0.1, 0.1, 0.99, backup);
+            sarsa.train(&mut env, 1).unwrap();
+            assert!(sarsa.get_q_table()[2].iter().any(|&q| q != 0.0), "{:?}", backup);
+        }
+    }
+
+    #[test]
+    fn test_every_backup_learns_grid_world_for_several_n() {
+        for backup in [NStepBackup::Sarsa, NStepBackup::ExpectedSarsa, NStepBackup::TreeBackup] {
+            for n in [1, 2, 4, 8] {
+                let mut env = TimeLimit::with_max_steps(GridWorld::new(), 50);
+                let mut sarsa = NStepSarsa::new(env.num_states(), env.num_actions(), n, 0.2, 0.2, 0.9, backup);
+                let rewards = sarsa.train(&mut env, 300).unwrap();
+                assert_eq!(rewards.len(), 300);
+
+                env.reset();
+                while !env.is_game_over() {
+                    env.step(sarsa.best_action_in(&env)).unwrap();
+                }
+                assert_eq!(env.score(), 1.0, "{:?} with n = {}", backup, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_time_limit_cut_offs_still_bootstrap() {
+        for backup in [NStepBackup::Sarsa, NStepBackup::ExpectedSarsa, NStepBackup::TreeBackup] {
+            let mut env = LineWorld::new();
+            let mut sarsa = NStepSarsa::new(env.num_states(), env.num_actions(), 2, 0.5, 0.0, 0.9, backup);
+            sarsa.train(&mut env, 50).unwrap();
+            let learned = sarsa.get_q_table()[2][1];
+            assert!(learned > 0.8, "{:?}: {}", backup, learned);
+
+            // Cut off one step from the end, the move towards the goal keeps its value
+            let mut env = TimeLimit::with_max_steps(LineWorld::new(), 1);
+            sarsa.train(&mut env, 50).unwrap();
+            let cut_off = sarsa.get_q_table()[2][1];
+            assert!((cut_off - learned).abs() < 0.05, "{:?}: {} then {}", backup, learned, cut_off);
+        }
+    }
+
+    #[test]
+    fn test_longer_returns_trade_bias_for_variance() {
+        // Early on, one-step returns are still biased towards the zero
+        // initial values while longer ones already carry the outcome
+        assert!(random_walk_error(4, 10) < random_walk_error(1, 10));
+
+        // Later, the noise of the sampled actions grows with n. The walk is
+        // deterministic given the action, so the one-step expected target
+        // has none and settles on the true values.
+        let errors: Vec<f32> = [1, 4, 16].iter().map(|&n| random_walk_error(n, 1000)).collect();
+        assert!(errors[0] < 1e-3, "{:?}", errors);
+        assert!(errors[0] < errors[1] && errors[1] < errors[2], "{:?}", errors);
+    }
+}
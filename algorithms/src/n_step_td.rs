@@ -0,0 +1,108 @@
+use environments::{Environment, EnvError};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::{PredictionAlgorithm, sample_policy_action};
+
+/// n-step TD prediction (Sutton & Barto, section 7.1).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NStepTD {
+    values: Vec<f32>,
+    n: usize,
+    alpha: f32,
+    gamma: f32,
+}
+
+impl NStepTD {
+    pub fn new(num_states: usize, n: usize, alpha: f32, gamma: f32) -> Self {
+        assert!(n > 0, "n must be at least 1");
+        NStepTD {
+            values: vec![0.0; num_states],
+            n,
+            alpha,
+            gamma,
+        }
+    }
+
+    // Updates the state visited at time tau using rewards tau+1..=min(tau+n, T)
+    fn update(&mut self, states: &[usize], rewards: &[f32], tau: usize, bootstrap: Option<usize>) {
+        let end = (tau + self.n).min(rewards.len());
+        let mut g = 0.0;
+        let mut discount = 1.0;
+        for &reward in &rewards[tau..end] {
+            g += discount * reward;
+            discount *= self.gamma;
+        }
+        if let Some(state) = bootstrap {
+            g += discount * self.values[state];
+        }
+
+        let state = states[tau];
+        self.values[state] += self.alpha * (g - self.values[state]);
+    }
+}
+
+impl PredictionAlgorithm for NStepTD {
+    fn run_episode<T: Environment>(&mut self, env: &mut T, policy: &[Vec<f32>], rng: &mut Xoshiro256PlusPlus) -> Result<f32, EnvError> {
+        env.reset();
+        // states[t] is S_t and rewards[t] is R_{t+1}
+        let mut states = vec![env.state_id()];
+        let mut rewards = Vec::new();
+
+        while !env.is_game_over() {
+            let state = *states.last().unwrap();
+            let action = sample_policy_action(policy, state, &env.available_actions(), rng);
+
+            let prev_score = env.score();
+            env.step(action)?;
+            rewards.push(env.score() - prev_score);
+            states.push(env.state_id());
+
+            // Once n rewards are known, the state n steps back can be updated
+            if !env.is_game_over() && rewards.len() >= self.n {
+                let tau = rewards.len() - self.n;
+                self.update(&states, &rewards, tau, Some(states[tau + self.n]));
+            }
+        }
+
+        // Flush the remaining states with the returns cut at the end of the
+        // episode, which still bootstrap from its last state if it was truncated
+        let bootstrap = if env.terminated() { None } else { states.last().copied() };
+        for tau in rewards.len().saturating_sub(self.n)..rewards.len() {
+            self.update(&states, &rewards, tau, bootstrap);
+        }
+
+        Ok(rewards.iter().sum())
+    }
+
+    fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::random_walk::RandomWalk;
+    use crate::{uniform_policy, td_zero::TDZero, mc_prediction::{MonteCarloPrediction, VisitMode}};
+
+    fn values_after<P: PredictionAlgorithm>(mut prediction: P, episodes: usize) -> Vec<f32> {
+        let mut env = RandomWalk::with_size(5);
+        let policy = uniform_policy(env.num_states(), env.num_actions());
+        prediction.evaluate(&mut env, &policy, episodes, &[]).unwrap();
+        prediction.get_values().to_vec()
+    }
+
+    #[test]
+    fn test_one_step_matches_td_zero() {
+        assert_eq!(values_after(NStepTD::new(7, 1, 0.1, 1.0), 20), values_after(TDZero::new(7, 0.1, 1.0), 20));
+    }
+
+    #[test]
+    fn test_longer_n_than_the_episode_matches_monte_carlo() {
+        // Only the final flush runs, with the full returns
+        assert_eq!(
+            values_after(NStepTD::new(7, 1000, 0.1, 1.0), 20),
+            values_after(MonteCarloPrediction::new(7, 0.1, 1.0, VisitMode::EveryVisit), 20)
+        );
+    }
+}
@@ -2,6 +2,7 @@ use rand::Rng;
 use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
 use crate::RLAlgorithm;
+use environments::EnvError;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct OffPolicyMonteCarloControl {
@@ -57,10 +58,10 @@ impl OffPolicyMonteCarloControl {
             return 0;
         }
 
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rng();
         let total_visits: usize = self.visit_counts.values().sum();
 
-        if rng.gen::<f32>() < self.epsilon {
+        if rng.random::<f32>() < self.epsilon {
             // Utiliser UCB pour l'exploration informée
             available_actions.iter()
                 .max_by(|&&a1, &&a2| {
@@ -91,16 +92,12 @@ impl OffPolicyMonteCarloControl {
     fn generate_episode<T: environments::Environment>(
         &mut self,
         env: &mut T,
-    ) -> Vec<(usize, usize, f32)> {
+    ) -> Result<Vec<(usize, usize, f32)>, EnvError> {
         let mut episode = Vec::new();
         self.episode_states.clear();
         env.reset();
 
-        let max_steps = 100;
-        let mut steps = 0;
-        let mut cumulative_reward = 0.0;
-
-        while !env.is_game_over() && steps < max_steps {
+        while !env.is_game_over() {
             let state = env.state_id();
             let available_actions = env.available_actions();
 
@@ -115,44 +112,35 @@ impl OffPolicyMonteCarloControl {
             *self.visit_counts.entry((state, action)).or_insert(0) += 1;
 
             let old_score = env.score();
-            env.step(action);
+            env.step(action)?;
             let reward = env.score() - old_score;
 
-            cumulative_reward += reward;
-
-            // Ajuster la récompense en fonction du progrès
-            let adjusted_reward = if self.episode_states.len() > steps {
-                // Récompense bonus pour explorer de nouveaux états
-                reward + 0.1
-            } else {
-                // Pénalité pour revisiter les mêmes états
-                reward - 0.1 * (steps - self.episode_states.len()) as f32
-            };
-
-            episode.push((state, action, adjusted_reward));
-            steps += 1;
+            episode.push((state, action, reward));
         }
 
-        if steps == max_steps {
-            // Pénaliser les épisodes qui atteignent la limite de pas
-            episode.last_mut().map(|(_s, _a, r)| *r -= 1.0);
-        }
+        Ok(episode)
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
 
-        episode
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
     }
 }
 
 impl RLAlgorithm for OffPolicyMonteCarloControl {
-    fn train<T: environments::Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: environments::Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rewards_history = Vec::new();
         let mut current_epsilon = self.epsilon;
 
         for _ in 0..max_episodes {
-            let episode = self.generate_episode(env);
+            let episode = self.generate_episode(env)?;
             let mut g = 0.0;
             let mut w = 1.0;
 
-            for (t, &(state, action, reward)) in episode.iter().enumerate().rev() {
+            for &(state, action, reward) in episode.iter().rev() {
                 g = self.gamma * g + reward;
 
                 self.c_values[state][action] += w;
@@ -182,7 +170,7 @@ impl RLAlgorithm for OffPolicyMonteCarloControl {
             rewards_history.push(total_reward);
         }
 
-        rewards_history
+        Ok(rewards_history)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -1,6 +1,6 @@
 use rand::Rng;
 use std::collections::HashMap;
-use environments::Environment;
+use environments::{Environment, EnvError};
 use serde::{Serialize, Deserialize};
 use super::RLAlgorithm;
 
@@ -32,7 +32,7 @@ impl MonteCarloControl {
         }
     }
 
-    fn generate_episode<T: Environment>(&self, env: &mut T) -> Vec<(usize, usize, f32)> {
+    fn generate_episode<T: Environment>(&self, env: &mut T) -> Result<Vec<(usize, usize, f32)>, EnvError> {
         let mut episode = Vec::new();
         env.reset();
 
@@ -44,21 +44,21 @@ impl MonteCarloControl {
             let action = self.select_action(state, &available_actions);
 
             let prev_score = env.score();
-            env.step(action);
+            env.step(action)?;
             let reward = env.score() - prev_score;
 
             episode.push((state, action, reward));
         }
 
-        episode
+        Ok(episode)
     }
 
     fn select_action(&self, state: usize, available_actions: &[usize]) -> usize {
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rng();
 
-        if rng.gen::<f32>() < self.epsilon {
+        if rng.random::<f32>() < self.epsilon {
             // Random action with probability ε
-            available_actions[rng.gen_range(0..available_actions.len())]
+            available_actions[rng.random_range(0..available_actions.len())]
         } else {
             // Greedy action with probability 1-ε
             let mut best_action = available_actions[0];
@@ -98,15 +98,23 @@ impl MonteCarloControl {
             };
         }
     }
+
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
 }
 
 impl RLAlgorithm for MonteCarloControl {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rewards_history = Vec::new();
 
         for _ in 0..max_episodes {
             // Generate episode using current policy
-            let episode = self.generate_episode(env);
+            let episode = self.generate_episode(env)?;
             let total_reward: f32 = episode.iter().map(|(_,_,r)| r).sum();
             rewards_history.push(total_reward);
 
@@ -114,12 +122,12 @@ impl RLAlgorithm for MonteCarloControl {
             let mut g = 0.0;
             let mut visited = HashMap::new();
 
-            for (t, (state, action, reward)) in episode.iter().enumerate().rev() {
+            for (state, action, reward) in episode.iter().rev() {
                 g = self.gamma * g + reward;
 
                 // First-visit check
-                if !visited.contains_key(&(*state, *action)) {
-                    visited.insert((*state, *action), true);
+                if let std::collections::hash_map::Entry::Vacant(entry) = visited.entry((*state, *action)) {
+                    entry.insert(true);
 
                     // Update returns and Q-value
                     self.returns[*state][*action].push(g);
@@ -133,7 +141,7 @@ impl RLAlgorithm for MonteCarloControl {
             }
         }
 
-        rewards_history
+        Ok(rewards_history)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -150,4 +158,37 @@ impl RLAlgorithm for MonteCarloControl {
 
         best_action
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::blackjack::Blackjack;
+
+    #[test]
+    fn test_learns_to_stick_on_high_blackjack_hands() {
+        let mut env = Blackjack::with_seed(1);
+        let mut mc = MonteCarloControl::new(env.num_states(), env.num_actions(), 0.1, 1.0);
+        mc.train(&mut env, 50000).unwrap();
+
+        for state in 0..env.num_states() - 1 {
+            let (player_sum, _, usable_ace) = Blackjack::decode_state(state).unwrap();
+            if player_sum >= 19 && !usable_ace {
+                assert_eq!(mc.get_best_action(state, &[0, 1]), 0, "{:?}", Blackjack::decode_state(state));
+            }
+        }
+
+        // The optimal policy loses about 0.05 per game, hitting until 20
+        // about 0.35
+        let games = 10000;
+        let mut total = 0.0;
+        for _ in 0..games {
+            env.reset();
+            while !env.is_game_over() {
+                env.step(mc.best_action_in(&env)).unwrap();
+            }
+            total += env.score();
+        }
+        assert!(total / games as f32 > -0.12, "{}", total / games as f32);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::RLAlgorithm;
 use serde::{Serialize, Deserialize};
-use environments::Environment;
+use environments::{Environment, EnvError};
 use rand::Rng;
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -15,9 +15,9 @@ pub struct PolicyIteration {
 
 impl PolicyIteration {
     pub fn new(num_states: usize, num_actions: usize, gamma: f32, theta: f32) -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rng();
         let policy = (0..num_states)
-            .map(|_| rng.gen_range(0..num_actions))
+            .map(|_| rng.random_range(0..num_actions))
             .collect();
 
         PolicyIteration {
@@ -106,19 +106,27 @@ impl PolicyIteration {
     pub fn get_policy(&self) -> &[usize] {
         &self.policy
     }
+
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
 }
 
 impl RLAlgorithm for PolicyIteration {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut returns = Vec::new();
 
         self.num_states = env.num_states();
         self.num_actions = env.num_actions();
 
         // Initialize with random policy
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rng();
         self.policy = (0..self.num_states)
-            .map(|_| rng.gen_range(0..self.num_actions))
+            .map(|_| rng.random_range(0..self.num_actions))
             .collect();
         self.value = vec![0.0; self.num_states];
 
@@ -137,7 +145,7 @@ impl RLAlgorithm for PolicyIteration {
                 // Only choose from available actions
                 if !available_actions.is_empty() {
                     let action = self.get_best_action(state, &available_actions);
-                    env.step(action);
+                    env.step(action)?;
                     episode_reward += env.score();
                 } else {
                     break;
@@ -146,7 +154,7 @@ impl RLAlgorithm for PolicyIteration {
 
             returns.push(episode_reward);
         }
-        returns
+        Ok(returns)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -165,4 +173,24 @@ impl RLAlgorithm for PolicyIteration {
             available_actions[0]
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::monty_hall_paradox1::{MontyHall, Visibility};
+
+    #[test]
+    fn test_policy_iteration_monty_hall_always_switches() {
+        let mut env = MontyHall::new_with_visibility(Visibility::ObservationOnly);
+        let mut pi = PolicyIteration::new(env.num_states(), env.num_actions(), 1.0, 1e-6);
+        pi.policy_iteration(&mut env);
+
+        for chosen in 0..3 {
+            for revealed in (0..3).filter(|&r| r != chosen) {
+                assert_eq!(pi.get_policy()[chosen * 3 + revealed], 1);
+            }
+        }
+        assert!((pi.value[0] - 2.0 / 3.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,134 @@
+use environments::{Environment, EnvError};
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::RLAlgorithm;
+use crate::exploration::{epsilon_greedy, greedy};
+use crate::sarsa_lambda::{ActiveTraces, TraceKind};
+
+/// Watkins' Q(λ) (Sutton & Barto, section 12.10): Q-learning whose traces
+/// are cut as soon as an exploratory action is taken, since the greedy
+/// policy being learned would not have followed the rest of the episode.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QLambda {
+    q_table: Vec<Vec<f32>>,
+    lambda: f32,
+    alpha: f32,
+    epsilon: f32,
+    gamma: f32,
+    trace: TraceKind,
+}
+
+impl QLambda {
+    pub fn new(
+        num_states: usize,
+        num_actions: usize,
+        lambda: f32,
+        alpha: f32,
+        epsilon: f32,
+        gamma: f32,
+        trace: TraceKind,
+    ) -> Self {
+        QLambda {
+            q_table: vec![vec![0.0; num_actions]; num_states],
+            lambda,
+            alpha,
+            epsilon,
+            gamma,
+            trace,
+        }
+    }
+
+    pub fn get_q_table(&self) -> &Vec<Vec<f32>> {
+        &self.q_table
+    }
+}
+
+impl RLAlgorithm for QLambda {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut episode_rewards = Vec::new();
+
+        for _ in 0..max_episodes {
+            env.reset();
+            let mut traces = ActiveTraces::new();
+            let mut total_reward = 0.0;
+            let mut state = env.state_id();
+
+            let available_actions = env.available_actions();
+            if available_actions.is_empty() {
+                continue;
+            }
+            let mut action = epsilon_greedy(&self.q_table[state], &available_actions, self.epsilon, &mut rng);
+
+            while !env.is_game_over() {
+                let prev_score = env.score();
+                env.step(action)?;
+                let reward = env.score() - prev_score;
+                total_reward += reward;
+
+                let next_state = env.state_id();
+                let next_available_actions = env.available_actions();
+                let next = if env.terminated() || next_available_actions.is_empty() {
+                    None
+                } else {
+                    let next_action = epsilon_greedy(&self.q_table[next_state], &next_available_actions, self.epsilon, &mut rng);
+                    let best_action = greedy(&self.q_table[next_state], &next_available_actions);
+                    Some((next_action, best_action))
+                };
+
+                let next_value = next.map_or(0.0, |(_, best)| self.q_table[next_state][best]);
+                let td_error = reward + self.gamma * next_value - self.q_table[state][action];
+                // Ties with the greedy action still count as following the greedy policy
+                let greedy_next = next.is_some_and(|(a, best)| {
+                    self.q_table[next_state][a] == self.q_table[next_state][best]
+                });
+                let decay = if greedy_next { self.gamma * self.lambda } else { 0.0 };
+
+                traces.visit(self.trace, state, action, self.alpha);
+                traces.update(&mut self.q_table, self.alpha * td_error, decay);
+
+                match next {
+                    Some((a, _)) => {
+                        state = next_state;
+                        action = a;
+                    }
+                    None => break,
+                }
+            }
+
+            episode_rewards.push(total_reward);
+        }
+
+        Ok(episode_rewards)
+    }
+
+    fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
+        greedy(&self.q_table[state], available_actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::grid_world::GridWorld;
+    use environments::wrappers::TimeLimit;
+
+    #[test]
+    fn test_q_lambda_finds_the_goal_on_grid_world() {
+        let mut env = TimeLimit::with_max_steps(GridWorld::new(), 50);
+        let mut q = QLambda::new(env.num_states(), env.num_actions(), 0.8, 0.2, 0.2, 0.9, TraceKind::Replacing);
+        let rewards = q.train(&mut env, 300).unwrap();
+        assert_eq!(rewards.len(), 300);
+
+        // From (1, 1) the greedy path leads to the goal at (0, 0)
+        env.reset();
+        let mut steps = 0;
+        while !env.is_game_over() {
+            env.step(q.best_action_in(&env)).unwrap();
+            steps += 1;
+        }
+        assert_eq!(env.score(), 1.0);
+        assert_eq!(steps, 2);
+    }
+}
@@ -1,7 +1,8 @@
-use environments::Environment;
+use environments::{Environment, EnvError};
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256PlusPlus;
 use crate::RLAlgorithm;
+use crate::exploration::epsilon_greedy;
 
 use serde::{Serialize, Deserialize};
 
@@ -34,7 +35,7 @@ impl QLearning {
 }
 
 impl RLAlgorithm for QLearning {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
         let mut episode_rewards = Vec::new();
 
@@ -46,15 +47,11 @@ impl RLAlgorithm for QLearning {
             while !env.is_game_over() {
                 // Get available actions and choose one using epsilon-greedy policy
                 let aa = env.available_actions();
-                let a = if rand::random::<f32>() <= self.epsilon {
-                    *aa.choose(&mut rng).unwrap()
-                } else {
-                    self.get_best_action(s, &aa)
-                };
+                let a = epsilon_greedy(&self.q_table[s], &aa, self.epsilon, &mut rng);
 
                 // Take action and observe reward and next state
                 let prev_score = env.score();
-                env.step(a);
+                env.step(a)?;
                 let r = env.score() - prev_score;
                 total_reward += r;
 
@@ -62,7 +59,7 @@ impl RLAlgorithm for QLearning {
                 let s_next = env.state_id();
 
                 // Calculate target Q-value
-                let max_q_next = if env.is_game_over() {
+                let max_q_next = if env.terminated() {
                     0.0
                 } else {
                     let aa_next = env.available_actions();
@@ -80,7 +77,7 @@ impl RLAlgorithm for QLearning {
             episode_rewards.push(total_reward);
         }
 
-        episode_rewards
+        Ok(episode_rewards)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -97,4 +94,52 @@ impl RLAlgorithm for QLearning {
 
         best_action
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One-step game whose backend fails on the 50th step overall.
+    #[derive(Clone)]
+    struct FlakyEnv {
+        total_steps: usize,
+        done: bool,
+    }
+
+    impl Environment for FlakyEnv {
+        fn num_states(&self) -> usize { 2 }
+        fn num_actions(&self) -> usize { 2 }
+        fn state_id(&self) -> usize { self.done as usize }
+        fn reset(&mut self) { self.done = false; }
+        fn is_game_over(&self) -> bool { self.done }
+        fn available_actions(&self) -> Vec<usize> {
+            if self.done { vec![] } else { vec![0, 1] }
+        }
+        fn score(&self) -> f32 { 0.0 }
+        fn step(&mut self, action: usize) -> Result<(), EnvError> {
+            self.check_action(action)?;
+            self.total_steps += 1;
+            if self.total_steps == 50 {
+                return Err(EnvError::Backend("connection lost".to_string()));
+            }
+            self.done = true;
+            Ok(())
+        }
+        fn display(&self) {}
+        fn fresh(&self) -> Self { FlakyEnv { done: false, ..*self } }
+        fn snapshot(&self) -> Result<Self, EnvError> { Ok(self.clone()) }
+    }
+
+    #[test]
+    fn test_training_surfaces_environment_errors() {
+        let mut env = FlakyEnv { total_steps: 0, done: false };
+        let mut agent = QLearning::new(2, 2, 0.1, 0.1, 0.9);
+        let result = agent.train(&mut env, 1000);
+        assert_eq!(result, Err(EnvError::Backend("connection lost".to_string())));
+
+        assert_eq!(env.step(2), Err(EnvError::IllegalAction { action: 2, legal: vec![0, 1] }));
+        env.step(0).unwrap();
+        assert_eq!(env.step(0), Err(EnvError::GameOver));
+    }
+}
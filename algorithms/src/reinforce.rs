@@ -1,6 +1,7 @@
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256PlusPlus;
-use environments::Environment;
+use environments::{Environment, EnvError};
+use environments::vec_env::VecEnv;
 use serde::{Serialize, Deserialize};
 use crate::RLAlgorithm;
 
@@ -31,7 +32,7 @@ impl Reinforce {
 
     fn sample_action(&self, state: usize, rng: &mut impl Rng) -> usize {
         let probs = self.softmax(state);
-        let r: f32 = rng.gen(); // Número aleatorio en [0,1)
+        let r: f32 = rng.random(); // Número aleatorio en [0,1)
         let mut cumulative = 0.0;
         for (i, p) in probs.iter().enumerate() {
             cumulative += *p;
@@ -42,10 +43,68 @@ impl Reinforce {
         // Por seguridad, retorna la última acción.
         self.policy[state].len() - 1
     }
+
+    // Actualización de la política para cada paso del episodio.
+    fn update(&mut self, episode: &[(usize, usize, f32)]) {
+        let n = episode.len();
+        for t in 0..n {
+            let mut g = 0.0;
+            let mut discount = 1.0;
+            for &(_, _, reward) in &episode[(t + 1)..n] {
+                g += discount * reward;
+                discount *= self.gamma;
+            }
+            // Factor de actualización: α · (γ^t) · G.
+            let update_factor = self.alpha * self.gamma.powi(t as i32) * g;
+            let (state, action, _) = episode[t];
+            let probs = self.softmax(state);
+            // Actualiza para cada acción: suma el término de actualización.
+            for (a, (logit, p)) in self.policy[state].iter_mut().zip(probs.iter()).enumerate() {
+                let grad = if a == action { 1.0 } else { 0.0 } - p;
+                *logit += update_factor * grad;
+            }
+        }
+    }
+
+    /// Plays episodes in every environment of `envs` at once and updates the
+    /// policy as each one finishes. Returns the reward of the first
+    /// `max_episodes` episodes to finish.
+    pub fn train_vec<T: Environment>(&mut self, envs: &mut VecEnv<T>, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut rewards_per_episode = Vec::with_capacity(max_episodes);
+        let mut episodes: Vec<Vec<(usize, usize, f32)>> = vec![Vec::new(); envs.num_envs()];
+
+        envs.reset();
+        let mut states = envs.states();
+        while rewards_per_episode.len() < max_episodes {
+            let actions: Vec<usize> = states.iter().map(|&s| self.sample_action(s, &mut rng)).collect();
+            let batch = envs.step(&actions)?;
+
+            for (i, episode) in episodes.iter_mut().enumerate() {
+                episode.push((states[i], actions[i], batch.rewards[i]));
+                if let Some(total_reward) = batch.episode_returns[i] {
+                    rewards_per_episode.push(total_reward);
+                    self.update(episode);
+                    episode.clear();
+                }
+            }
+            states = batch.states;
+        }
+        rewards_per_episode.truncate(max_episodes);
+        Ok(rewards_per_episode)
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.policy.len()
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.policy.first().map_or(0, Vec::len)
+    }
 }
 
 impl RLAlgorithm for Reinforce {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
         let mut rewards_per_episode = Vec::with_capacity(max_episodes);
 
@@ -59,34 +118,16 @@ impl RLAlgorithm for Reinforce {
                 let state = env.state_id();
                 let action = self.sample_action(state, &mut rng);
                 let prev_score = env.score();
-                env.step(action);
+                env.step(action)?;
                 let reward = env.score() - prev_score;  // La recompensa se calcula como la diferencia en score.
                 total_reward += reward;
                 episode.push((state, action, reward));
             }
             rewards_per_episode.push(total_reward);
 
-            // Actualización de la política para cada paso del episodio.
-            let n = episode.len();
-            for t in 0..n {
-                let mut G = 0.0;
-                let mut discount = 1.0;
-                for k in (t + 1)..n {
-                    G += discount * episode[k].2;
-                    discount *= self.gamma;
-                }
-                // Factor de actualización: α · (γ^t) · G.
-                let update_factor = self.alpha * self.gamma.powi(t as i32) * G;
-                let (state, action, _) = episode[t];
-                let probs = self.softmax(state);
-                // Actualiza para cada acción: suma el término de actualización.
-                for a in 0..self.policy[state].len() {
-                    let grad = if a == action { 1.0 } else { 0.0 } - probs[a];
-                    self.policy[state][a] += update_factor * grad;
-                }
-            }
+            self.update(&episode);
         }
-        rewards_per_episode
+        Ok(rewards_per_episode)
     }
 
     /// Durante la evaluación, devuelve la acción con mayor probabilidad para el estado dado.
@@ -124,8 +165,17 @@ mod tests {
     fn test_reinforce_training() {
         let mut env = LineWorld::new();
         let mut reinforce = Reinforce::new(env.num_states(), env.num_actions(), 0.1, 0.99);
-        let rewards = reinforce.train(&mut env, 100);
+        let rewards = reinforce.train(&mut env, 100).unwrap();
         // Se deben generar 100 episodios.
         assert_eq!(rewards.len(), 100);
     }
+
+    #[test]
+    fn test_reinforce_learns_from_vectorised_environments() {
+        let mut envs = VecEnv::with_workers(&LineWorld::new(), 4, 2);
+        let mut reinforce = Reinforce::new(envs.num_states(), envs.num_actions(), 0.1, 0.99);
+        let rewards = reinforce.train_vec(&mut envs, 300).unwrap();
+        assert_eq!(rewards.len(), 300);
+        assert_eq!(reinforce.get_best_action(2, &[0, 1]), 1);
+    }
 }
@@ -1,8 +1,9 @@
-use environments::Environment;
+use environments::{Environment, EnvError};
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256PlusPlus;
 use serde::{Serialize, Deserialize};
 use crate::RLAlgorithm;
+use crate::exploration::epsilon_greedy;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Sarsa {
@@ -39,11 +40,11 @@ impl Sarsa {
 }
 
 impl RLAlgorithm for Sarsa {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
         let mut episode_rewards = Vec::new();
 
-        for episode in 0..max_episodes {
+        for _ in 0..max_episodes {
             env.reset();
             let mut total_reward = 0.0;
             let mut state = env.state_id();
@@ -55,30 +56,22 @@ impl RLAlgorithm for Sarsa {
             }
 
             // Premier choix d'action
-            let mut action = if rand::random::<f32>() <= self.epsilon {
-                *available_actions.choose(&mut rng).unwrap()
-            } else {
-                self.get_best_action(state, &available_actions)
-            };
-
-            let mut steps = 0;
-            let max_steps = 25; // Limite de pas par épisode
+            let mut action = epsilon_greedy(&self.q_table[state], &available_actions, self.epsilon, &mut rng);
 
             // Boucle principale d'apprentissage
-            while !env.is_game_over() && steps < max_steps {
+            while !env.is_game_over() {
                 let prev_state = state;
                 let prev_action = action;
 
                 // Exécuter l'action
                 let prev_score = env.score();
-                env.step(action);
+                env.step(action)?;
                 let reward = env.score() - prev_score;
                 total_reward += reward;
 
                 state = env.state_id();
-                steps += 1;
 
-                if env.is_game_over() {
+                if env.terminated() {
                     // Mise à jour finale de Q
                     self.q_table[prev_state][prev_action] += self.alpha * (
                         reward - self.q_table[prev_state][prev_action]
@@ -93,11 +86,7 @@ impl RLAlgorithm for Sarsa {
                 }
 
                 // Sélectionner la prochaine action (ε-greedy)
-                let next_action = if rand::random::<f32>() <= self.epsilon {
-                    *next_available_actions.choose(&mut rng).unwrap()
-                } else {
-                    self.get_best_action(state, &next_available_actions)
-                };
+                let next_action = epsilon_greedy(&self.q_table[state], &next_available_actions, self.epsilon, &mut rng);
 
                 // Mise à jour de Q avec la règle SARSA
                 self.q_table[prev_state][prev_action] += self.alpha * (
@@ -112,7 +101,7 @@ impl RLAlgorithm for Sarsa {
             episode_rewards.push(total_reward);
         }
 
-        episode_rewards
+        Ok(episode_rewards)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -133,4 +122,23 @@ impl RLAlgorithm for Sarsa {
 
         best_action
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::line_world::LineWorld;
+    use environments::wrappers::TimeLimit;
+
+    #[test]
+    fn test_time_limit_cut_offs_still_bootstrap() {
+        let mut sarsa = Sarsa::new(5, 2, 0.5, 0.0, 0.9);
+        sarsa.train(&mut LineWorld::new(), 50).unwrap();
+        let before = sarsa.get_q_table()[2][1];
+        assert!(before > 0.8, "{}", before);
+
+        // Every episode now stops after one step, in a state that is not
+        // terminal: moving right from the start must keep its value
+        sarsa.train(&mut TimeLimit::with_max_steps(LineWorld::new(), 1), 50).unwrap();
+        assert!((sarsa.get_q_table()[2][1] - before).abs() < 0.05, "{:?}", sarsa.get_q_table());
+    }
+}
@@ -0,0 +1,194 @@
+use environments::{Environment, EnvError};
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::RLAlgorithm;
+use crate::exploration::{epsilon_greedy, greedy};
+
+/// How the eligibility trace of a state-action pair grows when it is visited.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TraceKind {
+    /// Adds 1 on every visit.
+    Accumulating,
+    /// Resets to 1 on every visit.
+    Replacing,
+    /// Adds 1 after shrinking by `alpha`, as in true online TD(λ).
+    Dutch,
+}
+
+impl TraceKind {
+    pub(crate) fn visit(self, trace: &mut f32, alpha: f32) {
+        match self {
+            TraceKind::Accumulating => *trace += 1.0,
+            TraceKind::Replacing => *trace = 1.0,
+            TraceKind::Dutch => *trace = (1.0 - alpha) * *trace + 1.0,
+        }
+    }
+}
+
+// Traces below this are dropped: their updates no longer matter
+const MIN_TRACE: f32 = 1e-4;
+
+/// Eligibility traces of the recently visited state-action pairs, so that a
+/// step costs the number of pairs still eligible instead of the size of the
+/// Q-table.
+pub(crate) struct ActiveTraces {
+    traces: Vec<(usize, usize, f32)>,
+}
+
+impl ActiveTraces {
+    pub(crate) fn new() -> Self {
+        ActiveTraces { traces: Vec::new() }
+    }
+
+    pub(crate) fn visit(&mut self, kind: TraceKind, state: usize, action: usize, alpha: f32) {
+        let index = match self.traces.iter().position(|&(s, a, _)| s == state && a == action) {
+            Some(index) => index,
+            None => {
+                self.traces.push((state, action, 0.0));
+                self.traces.len() - 1
+            }
+        };
+        kind.visit(&mut self.traces[index].2, alpha);
+    }
+
+    /// Moves every eligible value by `step` times its trace, then decays the
+    /// traces by `decay`.
+    pub(crate) fn update(&mut self, q_table: &mut [Vec<f32>], step: f32, decay: f32) {
+        for (state, action, trace) in &mut self.traces {
+            q_table[*state][*action] += step * *trace;
+            *trace *= decay;
+        }
+        self.traces.retain(|&(_, _, trace)| trace.abs() >= MIN_TRACE);
+    }
+}
+
+/// Tabular Sarsa(λ) (Sutton & Barto, section 12.7): every TD error also
+/// updates the recently visited pairs, in proportion to their traces.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SarsaLambda {
+    q_table: Vec<Vec<f32>>,
+    lambda: f32,
+    alpha: f32,
+    epsilon: f32,
+    gamma: f32,
+    trace: TraceKind,
+}
+
+impl SarsaLambda {
+    pub fn new(
+        num_states: usize,
+        num_actions: usize,
+        lambda: f32,
+        alpha: f32,
+        epsilon: f32,
+        gamma: f32,
+        trace: TraceKind,
+    ) -> Self {
+        SarsaLambda {
+            q_table: vec![vec![0.0; num_actions]; num_states],
+            lambda,
+            alpha,
+            epsilon,
+            gamma,
+            trace,
+        }
+    }
+
+    pub fn get_q_table(&self) -> &Vec<Vec<f32>> {
+        &self.q_table
+    }
+}
+
+impl RLAlgorithm for SarsaLambda {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut episode_rewards = Vec::new();
+
+        for _ in 0..max_episodes {
+            env.reset();
+            let mut traces = ActiveTraces::new();
+            let mut total_reward = 0.0;
+            let mut state = env.state_id();
+
+            let available_actions = env.available_actions();
+            if available_actions.is_empty() {
+                continue;
+            }
+            let mut action = epsilon_greedy(&self.q_table[state], &available_actions, self.epsilon, &mut rng);
+
+            while !env.is_game_over() {
+                let prev_score = env.score();
+                env.step(action)?;
+                let reward = env.score() - prev_score;
+                total_reward += reward;
+
+                let next_state = env.state_id();
+                let next_available_actions = env.available_actions();
+                let next_action = if env.terminated() || next_available_actions.is_empty() {
+                    None
+                } else {
+                    Some(epsilon_greedy(&self.q_table[next_state], &next_available_actions, self.epsilon, &mut rng))
+                };
+
+                let next_value = next_action.map_or(0.0, |a| self.q_table[next_state][a]);
+                let td_error = reward + self.gamma * next_value - self.q_table[state][action];
+
+                traces.visit(self.trace, state, action, self.alpha);
+                traces.update(&mut self.q_table, self.alpha * td_error, self.gamma * self.lambda);
+
+                match next_action {
+                    Some(a) => {
+                        state = next_state;
+                        action = a;
+                    }
+                    None => break,
+                }
+            }
+
+            episode_rewards.push(total_reward);
+        }
+
+        Ok(episode_rewards)
+    }
+
+    fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
+        greedy(&self.q_table[state], available_actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::line_world::LineWorld;
+
+    #[test]
+    fn test_traces_reach_the_start_state_in_one_episode() {
+        for trace in [TraceKind::Accumulating, TraceKind::Replacing, TraceKind::Dutch] {
+            let mut env = LineWorld::new();
+            let mut sarsa = SarsaLambda::new(env.num_states(), env.num_actions(), 0.9, 0.1, 0.1, 0.99, trace);
+            sarsa.train(&mut env, 1).unwrap();
+            // Line World needs at least two steps from its start, which one-step Sarsa cannot back up
+            assert!(sarsa.get_q_table()[2].iter().any(|&q| q != 0.0), "{:?}", trace);
+
+            sarsa.train(&mut env, 200).unwrap();
+            assert_eq!(sarsa.get_best_action(2, &[0, 1]), 1, "{:?}", trace);
+        }
+    }
+
+    #[test]
+    fn test_decayed_traces_are_dropped() {
+        let mut q_table = vec![vec![0.0; 2]; 3];
+        let mut traces = ActiveTraces::new();
+        traces.visit(TraceKind::Accumulating, 0, 1, 0.1);
+        traces.visit(TraceKind::Accumulating, 2, 0, 0.1);
+        traces.visit(TraceKind::Accumulating, 0, 1, 0.1);
+        assert_eq!(traces.traces.len(), 2);
+
+        traces.update(&mut q_table, 0.5, 0.5);
+        assert_eq!(q_table, vec![vec![0.0, 1.0], vec![0.0, 0.0], vec![0.5, 0.0]]);
+        // A zero decay, as when Q(λ) explores, empties the list
+        traces.update(&mut q_table, 0.5, 0.0);
+        assert!(traces.traces.is_empty());
+    }
+}
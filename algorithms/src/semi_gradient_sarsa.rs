@@ -1,152 +1,287 @@
-use environments::Environment;
+use environments::{Environment, EnvError};
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256PlusPlus;
 use crate::RLAlgorithm;
+use crate::exploration::epsilon_greedy;
+use crate::features::{FeatureExtractor, LinearQ};
+use crate::tile_coding::TileCoder;
 use serde::{Serialize, Deserialize};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SemiGradientSarsa {
-    weights: Vec<f32>,
-    num_features: usize,
+    q: LinearQ,
     alpha: f32,
     epsilon: f32,
     gamma: f32,
+    #[serde(default)]
+    lambda: Option<f32>,
 }
 
 impl SemiGradientSarsa {
+    /// Linear Sarsa on one-hot state features, which behaves like tabular Sarsa.
     pub fn new(
-        num_features: usize,
+        num_states: usize,
+        num_actions: usize,
+        alpha: f32,
+        epsilon: f32,
+        gamma: f32,
+    ) -> Self {
+        Self::with_features(FeatureExtractor::OneHot { num_states }, num_actions, alpha, epsilon, gamma)
+    }
+
+    pub fn with_features(
+        extractor: FeatureExtractor,
+        num_actions: usize,
         alpha: f32,
         epsilon: f32,
         gamma: f32,
     ) -> Self {
         SemiGradientSarsa {
-            weights: vec![0.0; num_features],
-            num_features,
+            q: LinearQ::new(extractor, num_actions, || 0.0),
             alpha,
             epsilon,
             gamma,
+            lambda: None,
         }
     }
 
-    fn compute_features(&self, state: usize, action: usize) -> Vec<f32> {
-        let mut features = vec![0.0; self.num_features];
+    /// Trains with true online Sarsa(λ) (Sutton & Barto, section 12.8)
+    /// instead of one-step Sarsa, using dutch traces over the weights.
+    pub fn with_true_online_lambda(mut self, lambda: f32) -> Self {
+        self.lambda = Some(lambda);
+        self
+    }
+
+    /// Linear Sarsa on the tiles of `coder`, with one block of weights per
+    /// action. `alpha` is shared between the tilings, as an observation
+    /// activates one tile in each.
+    pub fn with_tile_coding(
+        coder: TileCoder,
+        num_actions: usize,
+        alpha: f32,
+        epsilon: f32,
+        gamma: f32,
+    ) -> Self {
+        let alpha = alpha / coder.tilings() as f32;
+        Self::with_features(FeatureExtractor::TileCoding(coder), num_actions, alpha, epsilon, gamma)
+    }
+
+    fn choose_action(&self, features: &[f32], available_actions: &[usize], rng: &mut Xoshiro256PlusPlus) -> usize {
+        epsilon_greedy(&self.q.values(features), available_actions, self.epsilon, rng)
+    }
+
+    fn train_true_online<T: Environment>(&mut self, env: &mut T, max_episodes: usize, lambda: f32) -> Result<Vec<f32>, EnvError> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut episode_rewards = Vec::new();
+
+        for _ in 0..max_episodes {
+            env.reset();
+            let mut total_reward = 0.0;
+            let mut traces = vec![vec![0.0; self.q.num_features()]; self.q.num_actions()];
+            let mut q_old = 0.0;
+            let mut features = self.q.observe(env.state_id(), self.q.observation_of(env));
+
+            let available_actions = env.available_actions();
+            if available_actions.is_empty() {
+                continue;
+            }
+            let mut action = self.choose_action(&features, &available_actions, &mut rng);
+
+            while !env.is_game_over() {
+                let prev_score = env.score();
+                env.step(action)?;
+                let reward = env.score() - prev_score;
+                total_reward += reward;
+
+                let next_features = self.q.observe(env.state_id(), self.q.observation_of(env));
+                let next_available_actions = env.available_actions();
+                let next_action = if env.terminated() || next_available_actions.is_empty() {
+                    None
+                } else {
+                    Some(self.choose_action(&next_features, &next_available_actions, &mut rng))
+                };
+
+                // The features of a terminal state are all zero
+                let q = self.q.value(&features, action);
+                let q_next = next_action.map_or(0.0, |a| self.q.value(&next_features, a));
+                let td_error = reward + self.gamma * q_next - q;
+
+                // Dutch trace: z = γλz + (1 - αγλ z·x) x, where x is non-zero only in the block of `action`
+                let decay = self.gamma * lambda;
+                let overlap: f32 = traces[action].iter().zip(&features).map(|(z, x)| z * x).sum();
+                traces.iter_mut().flatten().for_each(|z| *z *= decay);
+                let scale = 1.0 - self.alpha * decay * overlap;
+                for (z, x) in traces[action].iter_mut().zip(&features) {
+                    *z += scale * x;
+                }
+
+                self.q.update_traced(&traces, self.alpha * (td_error + q - q_old));
+                self.q.update(&features, action, -self.alpha * (q - q_old));
+
+                match next_action {
+                    Some(a) => {
+                        q_old = q_next;
+                        features = next_features;
+                        action = a;
+                    }
+                    None => break,
+                }
+            }
+
+            episode_rewards.push(total_reward);
+        }
 
-        // Feature basique : état-action
-        let index = state * action % self.num_features;
-        features[index] = 1.0;
+        Ok(episode_rewards)
+    }
 
-        // Feature d'état
-        let state_index = state % self.num_features;
-        features[state_index] = 1.0;
+    /// Number of state ids the model knows, or `None` when it learned from
+    /// observations and only knows the states it remembers one for.
+    pub fn num_states(&self) -> Option<usize> {
+        self.q.num_states()
+    }
 
-        features
+    /// Whether `get_best_action` can answer for `state`.
+    pub fn knows_state(&self, state: usize) -> bool {
+        self.q.knows_state(state)
     }
 
-    fn approximate_q_value(&self, state: usize, action: usize) -> f32 {
-        let features = self.compute_features(state, action);
-        features.iter()
-            .zip(self.weights.iter())
-            .map(|(f, w)| f * w)
-            .sum()
+    pub fn num_actions(&self) -> usize {
+        self.q.num_actions()
     }
 }
 
 impl RLAlgorithm for SemiGradientSarsa {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
+        if let Some(lambda) = self.lambda {
+            return self.train_true_online(env, max_episodes, lambda);
+        }
         let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
         let mut episode_rewards = Vec::new();
 
-        for episode in 0..max_episodes {
+        for _ in 0..max_episodes {
             env.reset();
             let mut total_reward = 0.0;
-            let mut state = env.state_id();
+            let mut features = self.q.observe(env.state_id(), self.q.observation_of(env));
 
             let available_actions = env.available_actions();
             if available_actions.is_empty() {
                 continue;
             }
 
-            let mut action = if rand::random::<f32>() <= self.epsilon {
-                *available_actions.choose(&mut rng).unwrap()
-            } else {
-                self.get_best_action(state, &available_actions)
-            };
-
-            let mut steps = 0;
-            let max_steps = 25; // Limite de pas par épisode
-
-            while !env.is_game_over() && steps < max_steps {
-                let prev_state = state;
-                let prev_action = action;
+            let mut action = self.choose_action(&features, &available_actions, &mut rng);
 
+            while !env.is_game_over() {
                 // Exécuter l'action
                 let prev_score = env.score();
-                env.step(action);
+                env.step(action)?;
                 let reward = env.score() - prev_score;
                 total_reward += reward;
 
-                state = env.state_id();
-                steps += 1;
-
                 // Gestion de l'état terminal
-                if env.is_game_over() {
-                    let td_error = reward - self.approximate_q_value(prev_state, prev_action);
-                    let gradient = self.compute_features(prev_state, prev_action);
-
-                    for i in 0..self.weights.len() {
-                        self.weights[i] += self.alpha * td_error * gradient[i];
-                    }
+                if env.terminated() {
+                    let td_error = reward - self.q.value(&features, action);
+                    self.q.update(&features, action, self.alpha * td_error);
                     break;
                 }
 
+                let next_features = self.q.observe(env.state_id(), self.q.observation_of(env));
                 let next_available_actions = env.available_actions();
                 if next_available_actions.is_empty() {
                     break;
                 }
 
-                let next_action = if rand::random::<f32>() <= self.epsilon {
-                    *next_available_actions.choose(&mut rng).unwrap()
-                } else {
-                    self.get_best_action(state, &next_available_actions)
-                };
-
-                // Calcul de l'erreur TD
-                let current_q = self.approximate_q_value(prev_state, prev_action);
-                let next_q = self.approximate_q_value(state, next_action);
-                let td_error = reward + self.gamma * next_q - current_q;
+                let next_action = self.choose_action(&next_features, &next_available_actions, &mut rng);
 
-                // Mise à jour des poids
-                let gradient = self.compute_features(prev_state, prev_action);
-                for i in 0..self.weights.len() {
-                    self.weights[i] += self.alpha * td_error * gradient[i];
-                }
+                // Calcul de l'erreur TD et mise à jour des poids
+                let td_error = reward
+                    + self.gamma * self.q.value(&next_features, next_action)
+                    - self.q.value(&features, action);
+                self.q.update(&features, action, self.alpha * td_error);
 
+                features = next_features;
                 action = next_action;
             }
 
             episode_rewards.push(total_reward);
         }
 
-        episode_rewards
+        Ok(episode_rewards)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
         if available_actions.is_empty() {
             panic!("Pas d'actions disponibles pour l'état {}", state);
         }
+        self.q.best_action(&self.q.features_of_state(state), available_actions)
+    }
 
-        let mut best_action = available_actions[0];
-        let mut best_value = self.approximate_q_value(state, available_actions[0]);
+    fn best_action_in<T: Environment>(&self, env: &T) -> usize {
+        let features = self.q.features(env.state_id(), &self.q.observation_of(env));
+        self.q.best_action(&features, &env.available_actions())
+    }
+}
 
-        for &action in available_actions.iter().skip(1) {
-            let value = self.approximate_q_value(state, action);
-            if value > best_value {
-                best_action = action;
-                best_value = value;
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::grid_world::GridWorld;
+    use environments::line_world::LineWorld;
+    use environments::mountain_car::{MountainCar, MIN_POSITION, MAX_POSITION, MAX_SPEED};
+    use environments::wrappers::TimeLimit;
+
+    #[test]
+    fn test_semi_gradient_sarsa_on_grid_coordinates() {
+        let mut env = TimeLimit::with_max_steps(GridWorld::new(), 50);
+        let extractor = FeatureExtractor::rbf_grid(&[0.0, 0.0], &[3.0, 3.0], 4, 0.5);
+        let mut sarsa = SemiGradientSarsa::with_features(extractor, env.num_actions(), 0.1, 0.1, 0.99);
+        let rewards = sarsa.train(&mut env, 300).unwrap();
+        assert_eq!(rewards.len(), 300);
 
-        best_action
+        // From the start at (1, 1) the agent heads for the winning corner at (0, 0)
+        env.reset();
+        let action = sarsa.best_action_in(&env);
+        assert!(action == 0 || action == 3);
+        assert_eq!(sarsa.get_best_action(env.state_id(), &env.available_actions()), action);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_semi_gradient_sarsa_with_hashed_tiles() {
+        let mut env = TimeLimit::with_max_steps(LineWorld::new(), 20);
+        let coder = TileCoder::new(&[0.0], &[4.0], 4, 4).hashed(32);
+        let mut sarsa = SemiGradientSarsa::with_tile_coding(coder, env.num_actions(), 0.5, 0.1, 0.9);
+        let rewards = sarsa.train(&mut env, 200).unwrap();
+        assert_eq!(rewards.len(), 200);
+
+        env.reset();
+        assert_eq!(sarsa.best_action_in(&env), 1);
+    }
+
+    #[test]
+    fn test_semi_gradient_sarsa_solves_mountain_car_with_tile_coding() {
+        let mut env = MountainCar::with_seed(3);
+        let coder = TileCoder::new(&[MIN_POSITION, -MAX_SPEED], &[MAX_POSITION, MAX_SPEED], 8, 8);
+        // Zero weights are optimistic against rewards of -1, so no exploration is needed
+        let mut sarsa = SemiGradientSarsa::with_tile_coding(coder, env.num_actions(), 0.5, 0.0, 1.0);
+        let rewards = sarsa.train(&mut env, 50).unwrap();
+        let recent = rewards[40..].iter().sum::<f32>() / 10.0;
+        assert!(recent > -400.0, "Mean return of the last episodes: {}", recent);
+    }
+
+    #[test]
+    fn test_true_online_sarsa_lambda_learns_faster_on_mountain_car() {
+        let coder = TileCoder::new(&[MIN_POSITION, -MAX_SPEED], &[MAX_POSITION, MAX_SPEED], 8, 8);
+        let mut one_step = SemiGradientSarsa::with_tile_coding(coder.clone(), 3, 0.5, 0.0, 1.0);
+        let mut true_online = SemiGradientSarsa::with_tile_coding(coder, 3, 0.5, 0.0, 1.0)
+            .with_true_online_lambda(0.9);
+
+        let steps = |rewards: Vec<f32>| -rewards.iter().sum::<f32>();
+        let one_step_steps = steps(one_step.train(&mut MountainCar::with_seed(3), 20).unwrap());
+        let true_online_steps = steps(true_online.train(&mut MountainCar::with_seed(3), 20).unwrap());
+        assert!(
+            true_online_steps < one_step_steps,
+            "True online: {} steps, one-step: {} steps",
+            true_online_steps,
+            one_step_steps
+        );
+    }
+}
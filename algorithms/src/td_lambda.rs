@@ -0,0 +1,79 @@
+use environments::{Environment, EnvError};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::{PredictionAlgorithm, sample_policy_action};
+
+/// Online tabular TD(λ) with accumulating eligibility traces.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TDLambda {
+    values: Vec<f32>,
+    lambda: f32,
+    alpha: f32,
+    gamma: f32,
+}
+
+impl TDLambda {
+    pub fn new(num_states: usize, lambda: f32, alpha: f32, gamma: f32) -> Self {
+        TDLambda {
+            values: vec![0.0; num_states],
+            lambda,
+            alpha,
+            gamma,
+        }
+    }
+}
+
+impl PredictionAlgorithm for TDLambda {
+    fn run_episode<T: Environment>(&mut self, env: &mut T, policy: &[Vec<f32>], rng: &mut Xoshiro256PlusPlus) -> Result<f32, EnvError> {
+        env.reset();
+        let mut traces = vec![0.0; self.values.len()];
+        let mut total_reward = 0.0;
+        let mut state = env.state_id();
+
+        while !env.is_game_over() {
+            let action = sample_policy_action(policy, state, &env.available_actions(), rng);
+
+            let prev_score = env.score();
+            env.step(action)?;
+            let reward = env.score() - prev_score;
+            total_reward += reward;
+
+            let next_state = env.state_id();
+            let next_value = if env.terminated() { 0.0 } else { self.values[next_state] };
+            let td_error = reward + self.gamma * next_value - self.values[state];
+
+            traces[state] += 1.0;
+            for (value, trace) in self.values.iter_mut().zip(traces.iter_mut()) {
+                *value += self.alpha * td_error * *trace;
+                *trace *= self.gamma * self.lambda;
+            }
+
+            state = next_state;
+        }
+
+        Ok(total_reward)
+    }
+
+    fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::random_walk::RandomWalk;
+    use crate::uniform_policy;
+
+    #[test]
+    fn test_td_lambda_random_walk() {
+        let mut env = RandomWalk::new();
+        let policy = uniform_policy(env.num_states(), env.num_actions());
+        let true_values = env.true_values();
+        let mut td = TDLambda::new(env.num_states(), 0.8, 0.05, 1.0);
+        let errors = td.evaluate(&mut env, &policy, 200, &true_values).unwrap();
+        assert_eq!(errors.len(), 200);
+        assert!(errors[199] < errors[0]);
+        assert!(errors[199] < 0.2);
+    }
+}
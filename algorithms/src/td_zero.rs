@@ -0,0 +1,70 @@
+use environments::{Environment, EnvError};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Serialize, Deserialize};
+use crate::{PredictionAlgorithm, sample_policy_action};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TDZero {
+    values: Vec<f32>,
+    alpha: f32,
+    gamma: f32,
+}
+
+impl TDZero {
+    pub fn new(num_states: usize, alpha: f32, gamma: f32) -> Self {
+        TDZero {
+            values: vec![0.0; num_states],
+            alpha,
+            gamma,
+        }
+    }
+}
+
+impl PredictionAlgorithm for TDZero {
+    fn run_episode<T: Environment>(&mut self, env: &mut T, policy: &[Vec<f32>], rng: &mut Xoshiro256PlusPlus) -> Result<f32, EnvError> {
+        env.reset();
+        let mut total_reward = 0.0;
+        let mut state = env.state_id();
+
+        while !env.is_game_over() {
+            let action = sample_policy_action(policy, state, &env.available_actions(), rng);
+
+            let prev_score = env.score();
+            env.step(action)?;
+            let reward = env.score() - prev_score;
+            total_reward += reward;
+
+            let next_state = env.state_id();
+            // Terminal states are worth 0
+            let next_value = if env.terminated() { 0.0 } else { self.values[next_state] };
+            self.values[state] += self.alpha * (reward + self.gamma * next_value - self.values[state]);
+
+            state = next_state;
+        }
+
+        Ok(total_reward)
+    }
+
+    fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::random_walk::RandomWalk;
+    use crate::uniform_policy;
+
+    #[test]
+    fn test_td_zero_converges_on_random_walk() {
+        let mut env = RandomWalk::with_size(5);
+        let policy = uniform_policy(env.num_states(), env.num_actions());
+        let true_values = env.true_values();
+        let mut td = TDZero::new(env.num_states(), 0.01, 1.0);
+        let errors = td.evaluate(&mut env, &policy, 2000, &true_values).unwrap();
+        // With a constant step size the estimates keep wandering a little
+        let late_error = errors[1900..].iter().sum::<f32>() / 100.0;
+        assert!(late_error < 0.05, "{}", late_error);
+    }
+}
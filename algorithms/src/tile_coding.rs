@@ -0,0 +1,140 @@
+use serde::{Serialize, Deserialize};
+
+/// Tile coding over a box of continuous observations: `tilings` grids with
+/// `tiles[d]` tiles along dimension `d`, each shifted by a fraction of a
+/// tile, so an observation activates exactly one tile per tiling.
+///
+/// Tiling `t` is shifted by `t * (2d + 1) / tilings` of a tile along
+/// dimension `d` rather than by the same amount everywhere, which keeps the
+/// tilings from all lining up along the diagonal. This works best with a
+/// power of two tilings, at least four per dimension.
+///
+/// With `hashed`, tiles are hashed into a table of fixed size instead of
+/// being numbered, so memory no longer grows with the resolution and the
+/// number of dimensions, at the cost of occasional collisions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TileCoder {
+    low: Vec<f32>,
+    high: Vec<f32>,
+    tiles: Vec<usize>,
+    tilings: usize,
+    table_size: Option<usize>,
+}
+
+impl TileCoder {
+    /// `tiles` tiles along every dimension.
+    pub fn new(low: &[f32], high: &[f32], tiles: usize, tilings: usize) -> Self {
+        Self::with_resolution(low, high, &vec![tiles; low.len()], tilings)
+    }
+
+    /// `tiles[d]` tiles along dimension `d`.
+    pub fn with_resolution(low: &[f32], high: &[f32], tiles: &[usize], tilings: usize) -> Self {
+        assert_eq!(low.len(), high.len(), "Bounds must have one value per dimension");
+        assert_eq!(low.len(), tiles.len(), "Resolution must have one value per dimension");
+        assert!(tiles.iter().all(|&n| n > 0) && tilings > 0, "Tile coding needs at least one tile and one tiling");
+        TileCoder { low: low.to_vec(), high: high.to_vec(), tiles: tiles.to_vec(), tilings, table_size: None }
+    }
+
+    /// Hashes tiles into `table_size` features.
+    pub fn hashed(mut self, table_size: usize) -> Self {
+        assert!(table_size > 0, "The hash table needs at least one entry");
+        self.table_size = Some(table_size);
+        self
+    }
+
+    pub fn tilings(&self) -> usize {
+        self.tilings
+    }
+
+    // Each tiling has one extra tile per dimension to cover its offset
+    fn tiles_per_tiling(&self) -> usize {
+        self.tiles.iter().map(|n| n + 1).product()
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.table_size.unwrap_or_else(|| self.tilings * self.tiles_per_tiling())
+    }
+
+    /// Index of the active tile in each tiling.
+    pub fn active_tiles(&self, observation: &[f32]) -> Vec<usize> {
+        (0..self.tilings)
+            .map(|tiling| {
+                let coordinates = self.coordinates(tiling, observation);
+                match self.table_size {
+                    Some(size) => hash(tiling, &coordinates) % size,
+                    None => {
+                        let tile = coordinates.iter()
+                            .zip(&self.tiles)
+                            .fold(0, |index, (&c, &n)| index * (n + 1) + c);
+                        tiling * self.tiles_per_tiling() + tile
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn coordinates(&self, tiling: usize, observation: &[f32]) -> Vec<usize> {
+        observation.iter()
+            .zip(self.low.iter().zip(&self.high))
+            .zip(&self.tiles)
+            .enumerate()
+            .map(|(d, ((&x, (&low, &high)), &n))| {
+                let offset = (tiling * (2 * d + 1) % self.tilings) as f32 / self.tilings as f32;
+                let scaled = (x - low) / (high - low) * n as f32 + offset;
+                (scaled.max(0.0) as usize).min(n)
+            })
+            .collect()
+    }
+}
+
+// FNV-1a, stable across runs and platforms so saved weights stay valid
+fn hash(tiling: usize, coordinates: &[usize]) -> usize {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for value in std::iter::once(tiling).chain(coordinates.iter().copied()) {
+        for byte in (value as u64).to_le_bytes() {
+            h ^= byte as u64;
+            h = h.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    h as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_tile_per_tiling() {
+        let coder = TileCoder::new(&[0.0, 0.0], &[1.0, 1.0], 4, 3);
+        assert_eq!(coder.num_features(), 3 * 25);
+
+        let tiles = coder.active_tiles(&[0.3, 0.9]);
+        assert_eq!(tiles.len(), 3);
+        for (tiling, &tile) in tiles.iter().enumerate() {
+            assert_eq!(tile / 25, tiling);
+        }
+        // Nearby points share most tiles, distant ones none
+        let near = coder.active_tiles(&[0.32, 0.9]);
+        let far = coder.active_tiles(&[0.9, 0.1]);
+        assert!(tiles.iter().zip(&near).filter(|(a, b)| a == b).count() >= 2);
+        assert!(tiles.iter().zip(&far).all(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn test_resolution_and_hashing() {
+        let coder = TileCoder::with_resolution(&[0.0, 0.0], &[1.0, 1.0], &[8, 2], 4);
+        assert_eq!(coder.num_features(), 4 * 9 * 3);
+        // The first dimension is finer, so moving along it changes tiles sooner
+        let tiles = coder.active_tiles(&[0.5, 0.5]);
+        let along_fine = coder.active_tiles(&[0.65, 0.5]);
+        let along_coarse = coder.active_tiles(&[0.5, 0.65]);
+        let shared = |other: &[usize]| tiles.iter().zip(other).filter(|(a, b)| a == b).count();
+        assert!(shared(&along_fine) < shared(&along_coarse));
+
+        let hashed = coder.clone().hashed(64);
+        assert_eq!(hashed.num_features(), 64);
+        let hashed_tiles = hashed.active_tiles(&[0.5, 0.5]);
+        assert!(hashed_tiles.iter().all(|&tile| tile < 64));
+        assert_eq!(hashed_tiles, hashed.active_tiles(&[0.5, 0.5]));
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use environments::{Environment, PolicyFn};
+use crate::{
+    RLAlgorithm,
+    q_learning::QLearning,
+    dyna_q::DynaQ,
+    policy_iteration::PolicyIteration,
+    value_iteration::ValueIteration,
+    on_montecarlo_control::MonteCarloControl,
+    off_montecarlo_control::OffPolicyMonteCarloControl,
+    sarsa::Sarsa,
+    reinforce::Reinforce,
+    semi_gradient_sarsa::SemiGradientSarsa,
+    dqn::DQN,
+    sarsa_lambda::SarsaLambda,
+    q_lambda::QLambda,
+    n_step_sarsa::NStepSarsa,
+};
+
+/// A trained agent of any algorithm, as saved in the `models` directory.
+#[derive(Serialize, Deserialize)]
+pub enum TrainedAI {
+    QLearning(QLearning),
+    DynaQ(DynaQ),
+    PolicyIteration(PolicyIteration),
+    ValueIteration(ValueIteration),
+    MonteCarloControl(MonteCarloControl),
+    OffPolicyMonteCarloControl(OffPolicyMonteCarloControl),
+    Sarsa(Sarsa),
+    Reinforce(Reinforce),
+    SemiGradientSarsa(SemiGradientSarsa),
+    DQN(DQN),
+    SarsaLambda(SarsaLambda),
+    QLambda(QLambda),
+    NStepSarsa(NStepSarsa),
+}
+
+impl TrainedAI {
+    pub fn save(&self, env_name: &str, algorithm_name: &str) -> std::io::Result<()> {
+        // Create models directory if it doesn't exist
+        fs::create_dir_all("models")?;
+
+        let filename = format!("models/{}_{}.json", env_name, algorithm_name);
+        self.to_file(&filename)?;
+        println!("Model saved to {}", filename);
+        Ok(())
+    }
+
+    pub fn load(env_name: &str, algorithm_name: &str) -> std::io::Result<Option<Self>> {
+        let filename = format!("models/{}_{}.json", env_name, algorithm_name);
+
+        if Path::new(&filename).exists() {
+            let model = Self::from_file(&filename)?;
+            println!("Model loaded from {}", filename);
+            Ok(Some(model))
+        } else {
+            println!("No saved model found at {}", filename);
+            Ok(None)
+        }
+    }
+
+    /// Writes the model as `save` does, to any path.
+    pub fn to_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Reads a model saved by `save` from any path.
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
+        match self {
+            TrainedAI::QLearning(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::DynaQ(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::PolicyIteration(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::ValueIteration(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::MonteCarloControl(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::OffPolicyMonteCarloControl(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::Sarsa(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::Reinforce(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::SemiGradientSarsa(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::DQN(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::SarsaLambda(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::QLambda(ai) => ai.get_best_action(state, available_actions),
+            TrainedAI::NStepSarsa(ai) => ai.get_best_action(state, available_actions),
+        }
+    }
+
+    /// The model as a policy, e.g. to play the opponent of Tic-Tac-Toe or
+    /// Rock Paper Scissors.
+    pub fn into_policy(self) -> PolicyFn {
+        Arc::new(move |state, available_actions| self.get_best_action(state, available_actions))
+    }
+
+    /// Number of state ids the model knows, or `None` when it learned from
+    /// observations and only knows the states it remembers one for.
+    pub fn num_states(&self) -> Option<usize> {
+        match self {
+            TrainedAI::QLearning(ai) => Some(ai.get_q_table().len()),
+            TrainedAI::DynaQ(ai) => Some(ai.get_q_table().len()),
+            TrainedAI::PolicyIteration(ai) => Some(ai.num_states()),
+            TrainedAI::ValueIteration(ai) => Some(ai.num_states()),
+            TrainedAI::MonteCarloControl(ai) => Some(ai.num_states()),
+            TrainedAI::OffPolicyMonteCarloControl(ai) => Some(ai.num_states()),
+            TrainedAI::Sarsa(ai) => Some(ai.get_q_table().len()),
+            TrainedAI::Reinforce(ai) => Some(ai.num_states()),
+            TrainedAI::SemiGradientSarsa(ai) => ai.num_states(),
+            TrainedAI::DQN(ai) => ai.num_states(),
+            TrainedAI::SarsaLambda(ai) => Some(ai.get_q_table().len()),
+            TrainedAI::QLambda(ai) => Some(ai.get_q_table().len()),
+            TrainedAI::NStepSarsa(ai) => Some(ai.get_q_table().len()),
+        }
+    }
+
+    /// Whether `get_best_action` can answer for `state`: a state id within
+    /// the model's table, or one a feature-based model remembers the
+    /// observation of.
+    pub fn knows_state(&self, state: usize) -> bool {
+        match self {
+            TrainedAI::SemiGradientSarsa(ai) => ai.knows_state(state),
+            TrainedAI::DQN(ai) => ai.knows_state(state),
+            _ => self.num_states().is_some_and(|num_states| state < num_states),
+        }
+    }
+
+    pub fn num_actions(&self) -> usize {
+        let table_actions = |table: &Vec<Vec<f32>>| table.first().map_or(0, Vec::len);
+        match self {
+            TrainedAI::QLearning(ai) => table_actions(ai.get_q_table()),
+            TrainedAI::DynaQ(ai) => table_actions(ai.get_q_table()),
+            TrainedAI::PolicyIteration(ai) => ai.num_actions(),
+            TrainedAI::ValueIteration(ai) => ai.num_actions(),
+            TrainedAI::MonteCarloControl(ai) => ai.num_actions(),
+            TrainedAI::OffPolicyMonteCarloControl(ai) => ai.num_actions(),
+            TrainedAI::Sarsa(ai) => table_actions(ai.get_q_table()),
+            TrainedAI::Reinforce(ai) => ai.num_actions(),
+            TrainedAI::SemiGradientSarsa(ai) => ai.num_actions(),
+            TrainedAI::DQN(ai) => ai.num_actions(),
+            TrainedAI::SarsaLambda(ai) => table_actions(ai.get_q_table()),
+            TrainedAI::QLambda(ai) => table_actions(ai.get_q_table()),
+            TrainedAI::NStepSarsa(ai) => table_actions(ai.get_q_table()),
+        }
+    }
+
+    /// Best action in the current state of `env`, using its observation when
+    /// the algorithm learned from features.
+    pub fn best_action_in<T: Environment>(&self, env: &T) -> usize {
+        match self {
+            TrainedAI::QLearning(ai) => ai.best_action_in(env),
+            TrainedAI::DynaQ(ai) => ai.best_action_in(env),
+            TrainedAI::PolicyIteration(ai) => ai.best_action_in(env),
+            TrainedAI::ValueIteration(ai) => ai.best_action_in(env),
+            TrainedAI::MonteCarloControl(ai) => ai.best_action_in(env),
+            TrainedAI::OffPolicyMonteCarloControl(ai) => ai.best_action_in(env),
+            TrainedAI::Sarsa(ai) => ai.best_action_in(env),
+            TrainedAI::Reinforce(ai) => ai.best_action_in(env),
+            TrainedAI::SemiGradientSarsa(ai) => ai.best_action_in(env),
+            TrainedAI::DQN(ai) => ai.best_action_in(env),
+            TrainedAI::SarsaLambda(ai) => ai.best_action_in(env),
+            TrainedAI::QLambda(ai) => ai.best_action_in(env),
+            TrainedAI::NStepSarsa(ai) => ai.best_action_in(env),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::rps::{Cyclic, HistoryEncoding, PolicyOpponent, RPS};
+    use environments::tic_tac_toe::{Opponent, TicTacToe};
+
+    // Writes `model` to disk and reads it back, as `save` and `load` do
+    fn reload(model: TrainedAI, name: &str) -> TrainedAI {
+        let path = std::env::temp_dir().join(format!("{}_{}.json", name, std::process::id()));
+        model.to_file(&path).unwrap();
+        let model = TrainedAI::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        model
+    }
+
+    #[test]
+    fn test_saved_model_plays_tic_tac_toe_opponent() {
+        let mut env = TicTacToe::new();
+        let mut q_learning = QLearning::new(env.num_states(), env.num_actions(), 0.1, 0.1, 0.9);
+        q_learning.train(&mut env, 500).unwrap();
+        let model = reload(TrainedAI::QLearning(q_learning), "tic_tac_toe_opponent");
+
+        let mut game = TicTacToe::new_with_opponent(Opponent::Policy(model.into_policy()));
+        for _ in 0..20 {
+            game.reset();
+            while !game.is_game_over() {
+                // The agent always takes the last free cell, the model must answer with a free one
+                let action = *game.available_actions().last().unwrap();
+                game.step(action).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_saved_model_plays_rps_opponent() {
+        // Against a player who always picks Rock, the model learns Paper
+        let mut env = RPS::iterated(10, 1, Box::new(Cyclic { sequence: vec![0] }));
+        let mut q_learning = QLearning::new(env.num_states(), env.num_actions(), 0.1, 0.1, 0.9);
+        q_learning.train(&mut env, 200).unwrap();
+        let model = reload(TrainedAI::QLearning(q_learning), "rps_opponent");
+
+        // Seen from its own side the game looks the same, so it beats our Rock every round
+        let opponent = PolicyOpponent { policy: model.into_policy(), history_len: 1, encoding: HistoryEncoding::Both };
+        let mut game = RPS::iterated(10, 1, Box::new(opponent));
+        while !game.is_game_over() {
+            game.step(0).unwrap();
+        }
+        assert_eq!(game.score(), -10.0);
+    }
+}
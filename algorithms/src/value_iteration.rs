@@ -1,5 +1,5 @@
 use std::f32;
-use environments::Environment;
+use environments::{Environment, EnvError};
 use crate::RLAlgorithm;
 
 use serde::{Serialize, Deserialize};
@@ -42,9 +42,8 @@ impl ValueIteration {
                 // Calculate Q(s,a) for each action
                 for a in 0..self.num_actions {
                     let mut q_value = 0.0;
-                    for s_next in 0..self.num_states {
-                        q_value += transition_probs[s][a][s_next] *
-                            (rewards[s][a] + self.gamma * self.values[s_next]);
+                    for (p, v_next) in transition_probs[s][a].iter().zip(self.values.iter()) {
+                        q_value += p * (rewards[s][a] + self.gamma * v_next);
                     }
                     self.q_values[s][a] = q_value;
                 }
@@ -76,10 +75,18 @@ impl ValueIteration {
     pub fn get_q_values(&self) -> &[Vec<f32>] {
         &self.q_values
     }
+
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.num_actions
+    }
 }
 
 impl RLAlgorithm for ValueIteration {
-    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Vec<f32> {
+    fn train<T: Environment>(&mut self, env: &mut T, max_episodes: usize) -> Result<Vec<f32>, EnvError> {
         let mut returns = Vec::new();
 
         if self.num_states != env.num_states() || self.num_actions != env.num_actions() {
@@ -100,7 +107,7 @@ impl RLAlgorithm for ValueIteration {
 
                 if !available_actions.is_empty() {
                     let action = self.get_best_action(state, &available_actions);
-                    env.step(action);
+                    env.step(action)?;
                     episode_reward += env.score();
                 } else {
                     break;
@@ -110,7 +117,7 @@ impl RLAlgorithm for ValueIteration {
             returns.push(episode_reward);
         }
 
-        returns
+        Ok(returns)
     }
 
     fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
@@ -128,4 +135,82 @@ impl RLAlgorithm for ValueIteration {
             .copied()
             .unwrap_or(available_actions[0])
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use environments::monty_hall::MontyHallN;
+    use environments::monty_hall_paradox1::{MontyHall, Visibility};
+    use environments::monty_hall_paradox2::MontyHall2;
+
+    fn solve(env: &MontyHallN) -> ValueIteration {
+        let mut vi = ValueIteration::new(env.num_states(), env.num_actions(), 1.0, 1e-6);
+        vi.value_iteration(&env.transition_probabilities(), &env.reward_function());
+        vi
+    }
+
+    #[test]
+    fn test_value_iteration_monty_hall_matches_analytic_win_probability() {
+        for (doors, opened, stages) in [(3, 1, 1), (4, 2, 1), (5, 1, 2), (6, 2, 2)] {
+            let env = MontyHallN::with_config(doors, opened, stages);
+            let vi = solve(&env);
+            assert!(
+                (vi.get_values()[env.state_id()] - env.switch_win_probability()).abs() < 1e-4,
+                "{} doors, {} opened, {} stages", doors, opened, stages
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_iteration_monty_hall_learns_to_switch() {
+        let mut env = MontyHallN::new();
+        let vi = solve(&env);
+        let mut wins = 0.0;
+
+        for _ in 0..1000 {
+            env.reset();
+            let mut first_choice = None;
+            while !env.is_game_over() {
+                let action = vi.get_best_action(env.state_id(), &env.available_actions());
+                if let Some(first) = first_choice {
+                    assert_ne!(action, first);
+                }
+                first_choice.get_or_insert(action);
+                env.step(action).unwrap();
+            }
+            wins += env.score();
+        }
+
+        assert!((wins / 1000.0 - env.switch_win_probability()).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_value_iteration_five_door_monty_hall_always_switches() {
+        let env = MontyHall2::new_with_visibility(Visibility::ObservationOnly);
+        let mut vi = ValueIteration::new(env.num_states(), env.num_actions(), 1.0, 1e-6);
+        vi.value_iteration(&env.transition_probabilities(), &env.reward_function());
+
+        for chosen in 0..5 {
+            for revealed in (0..5).filter(|&r| r != chosen) {
+                assert_eq!(vi.get_best_action(chosen * 5 + revealed, &[0, 1]), 1);
+            }
+        }
+        // Keeping wins 1/5 of the time, switching to one of the 3 other closed doors 4/15
+        assert!((vi.get_values()[0] - 4.0 / 15.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_value_iteration_exploits_leaky_monty_hall_state() {
+        let hidden = MontyHall::new_with_visibility(Visibility::ObservationOnly);
+        let leaky = MontyHall::new();
+        let mut vi = ValueIteration::new(hidden.num_states(), hidden.num_actions(), 1.0, 1e-6);
+        vi.value_iteration(&hidden.transition_probabilities(), &hidden.reward_function());
+        assert!((vi.get_values()[0] - 2.0 / 3.0).abs() < 1e-4);
+
+        // Knowing the winning door from the state id, every game is won
+        let mut vi = ValueIteration::new(leaky.num_states(), leaky.num_actions(), 1.0, 1e-6);
+        vi.value_iteration(&leaky.transition_probabilities(), &leaky.reward_function());
+        assert!((vi.get_values()[leaky.state_id()] - 1.0).abs() < 1e-4);
+    }
+}
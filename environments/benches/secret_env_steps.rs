@@ -0,0 +1,43 @@
+//! Steps per second on the bundled secret environments.
+//!
+//! Run with `cargo bench -p environments`. Set `SECRET_ENVS_LIB` to benchmark
+//! another build of the library.
+
+use std::time::Instant;
+use environments::Environment;
+use environments::secret_env::{SecretEnv, LIBRARY_PATH_VAR};
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+const STEPS: usize = 200_000;
+
+fn main() {
+    let path = std::env::var(LIBRARY_PATH_VAR)
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/../libs/libsecret_envs.so").to_string());
+    let count = match SecretEnv::count_available(&path) {
+        Ok(count) => count,
+        Err(e) => {
+            println!("Skipping benchmark: {}", e);
+            return;
+        }
+    };
+
+    for env_id in 0..count {
+        let mut env = SecretEnv::load(&path, env_id).unwrap();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        env.reset();
+
+        let start = Instant::now();
+        for _ in 0..STEPS {
+            if env.is_game_over() {
+                env.reset();
+            }
+            let action = *env.available_actions().choose(&mut rng).unwrap();
+            env.step(action).unwrap();
+            std::hint::black_box(env.state_id());
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+
+        println!("Secret Environment {}: {:.0} steps/s", env_id, STEPS as f64 / elapsed);
+    }
+}
@@ -0,0 +1,167 @@
+use rand::prelude::*;
+use rand_distr::{Distribution, Normal};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArmDistribution {
+    /// True values drawn from N(0, 1), rewards from N(q*(a), 1).
+    Gaussian,
+    /// Success probabilities drawn from U(0, 1), rewards in {0, 1}.
+    Bernoulli,
+}
+
+/// The k-armed testbed of Sutton & Barto (section 2.3).
+///
+/// There is a single state and every episode is a single pull, so the
+/// standard training loops can be reused unchanged. `reset` keeps the same
+/// arms; call `new_problem` to draw a fresh bandit. With a non-zero
+/// `drift_std` the true values take an independent N(0, drift_std) random
+/// walk after every pull (section 2.5).
+#[derive(Clone)]
+pub struct KArmedBandit {
+    true_values: Vec<f32>,
+    distribution: ArmDistribution,
+    drift_std: f32,
+    last_reward: Option<f32>,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl KArmedBandit {
+    pub fn new() -> Self {
+        Self::with_config(10, ArmDistribution::Gaussian, 0.0, 42)
+    }
+
+    pub fn with_config(k: usize, distribution: ArmDistribution, drift_std: f32, seed: u64) -> Self {
+        assert!(k > 0, "A bandit needs at least one arm");
+        let mut bandit = KArmedBandit {
+            true_values: vec![0.0; k],
+            distribution,
+            drift_std,
+            last_reward: None,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        bandit.new_problem();
+        bandit
+    }
+
+    /// Draws new true values for every arm.
+    pub fn new_problem(&mut self) {
+        let standard = Normal::new(0.0, 1.0).unwrap();
+        for value in self.true_values.iter_mut() {
+            *value = match self.distribution {
+                ArmDistribution::Gaussian => standard.sample(&mut self.rng),
+                ArmDistribution::Bernoulli => self.rng.random::<f32>(),
+            };
+        }
+        self.last_reward = None;
+    }
+
+    /// Expected reward of every arm.
+    pub fn true_values(&self) -> &[f32] {
+        &self.true_values
+    }
+
+    /// Arm with the highest expected reward.
+    pub fn optimal_action(&self) -> usize {
+        self.true_values.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(a, _)| a)
+            .unwrap()
+    }
+
+    /// Pulls an arm and returns its reward, without the episode bookkeeping of `step`.
+    pub fn pull(&mut self, arm: usize) -> f32 {
+        let reward = match self.distribution {
+            ArmDistribution::Gaussian => Normal::new(self.true_values[arm], 1.0).unwrap().sample(&mut self.rng),
+            ArmDistribution::Bernoulli => {
+                if self.rng.random::<f32>() < self.true_values[arm] { 1.0 } else { 0.0 }
+            }
+        };
+
+        if self.drift_std > 0.0 {
+            let drift = Normal::new(0.0, self.drift_std).unwrap();
+            for value in self.true_values.iter_mut() {
+                *value += drift.sample(&mut self.rng);
+                if self.distribution == ArmDistribution::Bernoulli {
+                    *value = value.clamp(0.0, 1.0);
+                }
+            }
+        }
+
+        reward
+    }
+}
+
+impl Default for KArmedBandit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for KArmedBandit {
+    fn num_states(&self) -> usize {
+        1
+    }
+
+    fn num_actions(&self) -> usize {
+        self.true_values.len()
+    }
+
+    fn state_id(&self) -> usize {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.last_reward = None;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.last_reward.is_some()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            (0..self.num_actions()).collect()
+        }
+    }
+
+    fn score(&self) -> f32 {
+        self.last_reward.unwrap_or(0.0)
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        self.last_reward = Some(self.pull(action));
+        Ok(())
+    }
+
+    fn display(&self) {
+        for (arm, value) in self.true_values.iter().enumerate() {
+            println!("Arm {}: q* = {:.3}", arm, value);
+        }
+        if let Some(reward) = self.last_reward {
+            println!("Last reward: {:.3}", reward);
+        }
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+}
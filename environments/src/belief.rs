@@ -0,0 +1,178 @@
+use crate::{Environment, EnvError};
+
+/// An environment whose outcome depends on a hidden variable the agent never
+/// sees directly, such as the winning door in Monty Hall.
+pub trait PartiallyObservable: Environment {
+    /// Number of values the hidden variable can take.
+    fn num_hidden_states(&self) -> usize;
+
+    /// Distribution of the hidden variable right after `reset`.
+    fn hidden_prior(&self) -> Vec<f32> {
+        vec![1.0 / self.num_hidden_states() as f32; self.num_hidden_states()]
+    }
+
+    /// Probability of moving from `state` to `next` with `action` when the
+    /// hidden variable is `hidden`.
+    fn observation_probability(&self, hidden: usize, state: usize, action: usize, next: usize) -> f32;
+}
+
+/// Wraps a partially observable environment and tracks the posterior over its
+/// hidden variable with Bayes' rule after every step.
+///
+/// The state id combines the wrapped state id with the belief, each
+/// probability rounded to `resolution` levels, so tabular agents learn on the
+/// belief MDP instead of on the raw observations.
+#[derive(Clone)]
+pub struct BeliefState<E: PartiallyObservable> {
+    env: E,
+    belief: Vec<f32>,
+    resolution: usize,
+}
+
+impl<E: PartiallyObservable> BeliefState<E> {
+    pub fn with_resolution(env: E, resolution: usize) -> Self {
+        assert!(resolution > 0, "The belief needs at least one level");
+        let belief = env.hidden_prior();
+        BeliefState { env, belief, resolution }
+    }
+
+    /// Current posterior over the hidden variable.
+    pub fn belief(&self) -> &[f32] {
+        &self.belief
+    }
+
+    pub fn most_likely_hidden(&self) -> usize {
+        self.belief.iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(h, _)| h)
+            .unwrap()
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn belief_buckets(&self) -> usize {
+        (self.resolution + 1).pow(self.belief.len() as u32)
+    }
+
+    fn belief_id(&self) -> usize {
+        self.belief.iter().rev().fold(0, |acc, &p| {
+            acc * (self.resolution + 1) + (p * self.resolution as f32).round() as usize
+        })
+    }
+}
+
+impl<E: PartiallyObservable> Environment for BeliefState<E> {
+    fn num_states(&self) -> usize {
+        self.env.num_states() * self.belief_buckets()
+    }
+
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    fn state_id(&self) -> usize {
+        self.env.state_id() * self.belief_buckets() + self.belief_id()
+    }
+
+    fn reset(&mut self) {
+        self.env.reset();
+        self.belief = self.env.hidden_prior();
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.env.is_game_over()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.env.available_actions()
+    }
+
+    fn score(&self) -> f32 {
+        self.env.score()
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        let state = self.env.state_id();
+        self.env.step(action)?;
+        let next = self.env.state_id();
+
+        let posterior: Vec<f32> = self.belief.iter()
+            .enumerate()
+            .map(|(hidden, p)| p * self.env.observation_probability(hidden, state, action, next))
+            .collect();
+        let total: f32 = posterior.iter().sum();
+        // An observation the model deems impossible leaves the belief unchanged
+        if total > 0.0 {
+            self.belief = posterior.iter().map(|p| p / total).collect();
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        self.env.display();
+        let belief: Vec<String> = self.belief.iter().map(|p| format!("{:.3}", p)).collect();
+        println!("Belief: [{}]", belief.join(", "));
+    }
+
+    fn truncated(&self) -> bool {
+        self.env.truncated()
+    }
+
+    fn fresh(&self) -> Self {
+        let env = self.env.fresh();
+        let belief = env.hidden_prior();
+        BeliefState { env, belief, resolution: self.resolution }
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        self.env.split_rng(stream);
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(BeliefState {
+            env: self.env.snapshot()?,
+            belief: self.belief.clone(),
+            resolution: self.resolution,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monty_hall_paradox1::{MontyHall, Visibility};
+
+    #[test]
+    fn test_monty_hall_belief_after_reveal() {
+        let mut env = BeliefState::with_resolution(MontyHall::with_seed(Visibility::ObservationOnly, 7), 6);
+        env.reset();
+        env.step(0).unwrap();
+
+        let revealed = (1..3).find(|&d| env.belief()[d] == 0.0).unwrap();
+        let other = 3 - revealed;
+        assert!((env.belief()[0] - 1.0 / 3.0).abs() < 1e-5);
+        assert!((env.belief()[other] - 2.0 / 3.0).abs() < 1e-5);
+        assert_eq!(env.most_likely_hidden(), other);
+    }
+
+    #[test]
+    fn test_following_the_belief_wins_two_thirds() {
+        let mut env = BeliefState::with_resolution(MontyHall::with_seed(Visibility::ObservationOnly, 7), 6);
+        let episodes = 3000;
+        let mut wins = 0.0;
+
+        for _ in 0..episodes {
+            env.reset();
+            env.step(0).unwrap();
+            // Keep (0) if the held door is the most likely one, switch (1) otherwise
+            let action = if env.most_likely_hidden() == 0 { 0 } else { 1 };
+            env.step(action).unwrap();
+            wins += env.score();
+        }
+
+        assert!((wins / episodes as f32 - 2.0 / 3.0).abs() < 0.05);
+    }
+}
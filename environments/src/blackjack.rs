@@ -0,0 +1,258 @@
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+const DEFAULT_SEED: u64 = 42;
+const NUM_PLAYER_SUMS: usize = 10;   // 12..=21
+const NUM_DEALER_CARDS: usize = 10;  // Ace (1) ..= 10
+const TERMINAL_STATE: usize = NUM_PLAYER_SUMS * NUM_DEALER_CARDS * 2;
+
+/// Blackjack as described in Sutton & Barto (Example 5.1), with an infinite deck.
+///
+/// The player is dealt cards automatically until their sum reaches 12, since
+/// hitting below 12 can never bust. Actions are Stick (0) and Hit (1); the
+/// dealer then hits until reaching 17 or more. The final reward is +1, 0 or -1.
+///
+/// Naturals are not scored apart: a player dealt an ace and a ten starts at
+/// 21 with a usable ace and plays the hand like any other, and a dealer
+/// natural is just a 21. Every episode thus starts with a decision.
+#[derive(Clone)]
+pub struct Blackjack {
+    player_sum: usize,
+    usable_ace: bool,
+    dealer_showing: usize,
+    dealer_sum: usize,
+    dealer_usable_ace: bool,
+    finished: bool,
+    reward: f32,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Blackjack {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let mut env = Blackjack {
+            player_sum: 0,
+            usable_ace: false,
+            dealer_showing: 0,
+            dealer_sum: 0,
+            dealer_usable_ace: false,
+            finished: false,
+            reward: 0.0,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        env.reset();
+        env
+    }
+
+    /// Decodes a state id into (player sum, dealer showing card, usable ace).
+    pub fn decode_state(state: usize) -> Option<(usize, usize, bool)> {
+        if state >= TERMINAL_STATE {
+            return None;
+        }
+        let usable_ace = state % 2 == 1;
+        let dealer_showing = (state / 2) % NUM_DEALER_CARDS + 1;
+        let player_sum = state / (2 * NUM_DEALER_CARDS) + 12;
+        Some((player_sum, dealer_showing, usable_ace))
+    }
+
+    pub fn player_sum(&self) -> usize {
+        self.player_sum
+    }
+
+    pub fn dealer_showing(&self) -> usize {
+        self.dealer_showing
+    }
+
+    pub fn usable_ace(&self) -> bool {
+        self.usable_ace
+    }
+
+    fn draw_card(&mut self) -> usize {
+        // Infinite deck: face cards count as 10, aces are drawn as 1
+        self.rng.random_range(1..=13).min(10)
+    }
+
+    // Adds a card to a hand, counting an ace as 11 when it does not bust
+    fn add_card(sum: &mut usize, usable_ace: &mut bool, card: usize) {
+        *sum += card;
+        if card == 1 && *sum + 10 <= 21 {
+            *sum += 10;
+            *usable_ace = true;
+        }
+        if *sum > 21 && *usable_ace {
+            *sum -= 10;
+            *usable_ace = false;
+        }
+    }
+
+    fn play_dealer(&mut self) {
+        while self.dealer_sum < 17 {
+            let card = self.draw_card();
+            Self::add_card(&mut self.dealer_sum, &mut self.dealer_usable_ace, card);
+        }
+    }
+
+    fn finish(&mut self, reward: f32) {
+        self.finished = true;
+        self.reward = reward;
+    }
+}
+
+impl Default for Blackjack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for Blackjack {
+    fn num_states(&self) -> usize {
+        TERMINAL_STATE + 1  // 10 player sums * 10 dealer cards * usable ace + terminal state
+    }
+
+    fn num_actions(&self) -> usize {
+        2  // Stick (0), Hit (1)
+    }
+
+    fn state_id(&self) -> usize {
+        if self.finished {
+            return TERMINAL_STATE;
+        }
+        (self.player_sum - 12) * NUM_DEALER_CARDS * 2
+            + (self.dealer_showing - 1) * 2
+            + self.usable_ace as usize
+    }
+
+    fn reset(&mut self) {
+        self.player_sum = 0;
+        self.usable_ace = false;
+        self.dealer_sum = 0;
+        self.dealer_usable_ace = false;
+        self.finished = false;
+        self.reward = 0.0;
+
+        while self.player_sum < 12 {
+            let card = self.draw_card();
+            Self::add_card(&mut self.player_sum, &mut self.usable_ace, card);
+        }
+
+        self.dealer_showing = self.draw_card();
+        let hidden_card = self.draw_card();
+        let showing = self.dealer_showing;
+        Self::add_card(&mut self.dealer_sum, &mut self.dealer_usable_ace, showing);
+        Self::add_card(&mut self.dealer_sum, &mut self.dealer_usable_ace, hidden_card);
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.finished
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            vec![0, 1]
+        }
+    }
+
+    fn score(&self) -> f32 {
+        self.reward
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        match action {
+            0 => {
+                self.play_dealer();
+                let reward = if self.dealer_sum > 21 || self.player_sum > self.dealer_sum {
+                    1.0
+                } else if self.player_sum == self.dealer_sum {
+                    0.0
+                } else {
+                    -1.0
+                };
+                self.finish(reward);
+            }
+            1 => {
+                let card = self.draw_card();
+                Self::add_card(&mut self.player_sum, &mut self.usable_ace, card);
+                if self.player_sum > 21 {
+                    self.finish(-1.0);
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        println!(
+            "Player sum: {}{}",
+            self.player_sum,
+            if self.usable_ace { " (usable ace)" } else { "" }
+        );
+        if self.finished {
+            println!("Dealer sum: {}", self.dealer_sum);
+            match self.reward {
+                r if r > 0.0 => println!("Player wins!"),
+                r if r < 0.0 => println!("Dealer wins!"),
+                _ => println!("Draw!"),
+            }
+        } else {
+            println!("Dealer showing: {}", self.dealer_showing);
+        }
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_id_round_trip() {
+        let mut env = Blackjack::with_seed(7);
+        for _ in 0..1000 {
+            env.reset();
+            let state = env.state_id();
+            assert!(state < TERMINAL_STATE);
+            assert_eq!(
+                Blackjack::decode_state(state),
+                Some((env.player_sum(), env.dealer_showing(), env.usable_ace()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_episodes_terminate_with_valid_reward() {
+        let mut env = Blackjack::with_seed(7);
+        for _ in 0..1000 {
+            env.reset();
+            while !env.is_game_over() {
+                let action = if env.player_sum() < 20 { 1 } else { 0 };
+                env.step(action).unwrap();
+            }
+            assert_eq!(env.state_id(), TERMINAL_STATE);
+            assert!([-1.0, 0.0, 1.0].contains(&env.score()));
+        }
+    }
+}
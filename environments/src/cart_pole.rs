@@ -0,0 +1,231 @@
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+const DEFAULT_SEED: u64 = 42;
+const GRAVITY: f32 = 9.8;
+const CART_MASS: f32 = 1.0;
+const POLE_MASS: f32 = 0.1;
+const POLE_HALF_LENGTH: f32 = 0.5;
+const FORCE: f32 = 10.0;
+const TAU: f32 = 0.02;  // Seconds between steps
+pub const X_THRESHOLD: f32 = 2.4;
+pub const THETA_THRESHOLD: f32 = 12.0 * std::f32::consts::PI / 180.0;
+pub const MAX_STEPS: usize = 500;
+const DEFAULT_BINS: usize = 6;
+// Ranges used to bin the unbounded velocities in `state_id`
+const X_DOT_RANGE: f32 = 3.0;
+const THETA_DOT_RANGE: f32 = 3.5;
+
+/// CartPole as in Barto, Sutton & Anderson (1983), with the constants and
+/// Euler integration of the usual Gym version.
+///
+/// A pole is hinged on a cart moving along a track. Actions push the cart
+/// left (0) or right (1); every step the pole stays up is worth 1. The
+/// episode fails when the pole leans more than 12 degrees or the cart leaves
+/// the track, and is truncated after 500 steps.
+///
+/// `observation` gives (x, x velocity, angle, angular velocity), while
+/// `state_id` cuts each of them into `bins` intervals for tabular learners.
+#[derive(Clone)]
+pub struct CartPole {
+    x: f32,
+    x_dot: f32,
+    theta: f32,
+    theta_dot: f32,
+    steps: usize,
+    bins: usize,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl CartPole {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_bins(seed, DEFAULT_BINS)
+    }
+
+    pub fn with_bins(seed: u64, bins: usize) -> Self {
+        assert!(bins > 0, "CartPole needs at least one bin per dimension");
+        let mut env = CartPole {
+            x: 0.0,
+            x_dot: 0.0,
+            theta: 0.0,
+            theta_dot: 0.0,
+            steps: 0,
+            bins,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        env.reset();
+        env
+    }
+
+    pub fn failed(&self) -> bool {
+        self.x.abs() > X_THRESHOLD || self.theta.abs() > THETA_THRESHOLD
+    }
+
+    fn bin(&self, value: f32, range: f32) -> usize {
+        let scaled = (value + range) / (2.0 * range) * self.bins as f32;
+        (scaled.max(0.0) as usize).min(self.bins - 1)
+    }
+}
+
+impl Default for CartPole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for CartPole {
+    fn num_states(&self) -> usize {
+        self.bins.pow(4)
+    }
+
+    fn num_actions(&self) -> usize {
+        2  // Left (0), Right (1)
+    }
+
+    fn state_id(&self) -> usize {
+        [
+            self.bin(self.x, X_THRESHOLD),
+            self.bin(self.x_dot, X_DOT_RANGE),
+            self.bin(self.theta, THETA_THRESHOLD),
+            self.bin(self.theta_dot, THETA_DOT_RANGE),
+        ]
+        .iter()
+        .fold(0, |id, &b| id * self.bins + b)
+    }
+
+    fn reset(&mut self) {
+        self.x = self.rng.random_range(-0.05..0.05);
+        self.x_dot = self.rng.random_range(-0.05..0.05);
+        self.theta = self.rng.random_range(-0.05..0.05);
+        self.theta_dot = self.rng.random_range(-0.05..0.05);
+        self.steps = 0;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.failed() || self.steps >= MAX_STEPS
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.terminated() {
+            vec![]
+        } else {
+            vec![0, 1]
+        }
+    }
+
+    fn score(&self) -> f32 {
+        self.steps as f32
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        let force = if action == 1 { FORCE } else { -FORCE };
+        let total_mass = CART_MASS + POLE_MASS;
+        let pole_moment = POLE_MASS * POLE_HALF_LENGTH;
+        let (sin, cos) = self.theta.sin_cos();
+
+        let temp = (force + pole_moment * self.theta_dot * self.theta_dot * sin) / total_mass;
+        let theta_acc = (GRAVITY * sin - cos * temp)
+            / (POLE_HALF_LENGTH * (4.0 / 3.0 - POLE_MASS * cos * cos / total_mass));
+        let x_acc = temp - pole_moment * theta_acc * cos / total_mass;
+
+        self.x += TAU * self.x_dot;
+        self.x_dot += TAU * x_acc;
+        self.theta += TAU * self.theta_dot;
+        self.theta_dot += TAU * theta_acc;
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn display(&self) {
+        let width = 41;
+        let column = ((self.x + X_THRESHOLD) / (2.0 * X_THRESHOLD) * (width - 1) as f32)
+            .clamp(0.0, (width - 1) as f32) as usize;
+        let track: String = (0..width).map(|i| if i == column { '#' } else { '_' }).collect();
+        println!("|{}|", track);
+        println!(
+            "x: {:.3}, angle: {:.1} degrees, step {}",
+            self.x,
+            self.theta.to_degrees(),
+            self.steps
+        );
+        if self.failed() {
+            println!("The pole fell!");
+        } else if self.truncated() {
+            println!("Balanced for {} steps!", MAX_STEPS);
+        }
+    }
+
+    // The episode ended on the step cap with the pole still up
+    fn truncated(&self) -> bool {
+        self.steps >= MAX_STEPS && !self.failed()
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        vec![self.x, self.x_dot, self.theta, self.theta_dot]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaning_controller_balances_until_truncation() {
+        let mut env = CartPole::with_seed(7);
+        for _ in 0..5 {
+            env.reset();
+            while !env.is_game_over() {
+                assert!(env.state_id() < env.num_states());
+                // Push the cart under the pole
+                let action = if env.theta + 0.5 * env.theta_dot > 0.0 { 1 } else { 0 };
+                env.step(action).unwrap();
+            }
+            assert!(env.truncated());
+            assert_eq!(env.available_actions(), vec![0, 1]);
+            assert_eq!(env.score(), MAX_STEPS as f32);
+        }
+    }
+
+    #[test]
+    fn test_pushing_one_way_drops_the_pole() {
+        let mut env = CartPole::with_seed(7);
+        while !env.is_game_over() {
+            env.step(1).unwrap();
+        }
+        assert!(env.failed());
+        assert!(env.score() < 50.0);
+        assert_eq!(env.step(0), Err(EnvError::GameOver));
+    }
+
+    #[test]
+    fn test_seed_fixes_the_initial_noise() {
+        let a = CartPole::with_seed(3);
+        let b = CartPole::with_seed(3);
+        assert_eq!(a.observation(), b.observation());
+        assert!(a.observation().iter().all(|v| v.abs() < 0.05));
+        assert_ne!(a.observation(), CartPole::with_seed(4).observation());
+    }
+}
@@ -0,0 +1,392 @@
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+const WIDTH: usize = 7;
+const HEIGHT: usize = 6;
+const EMPTY: u8 = 0;
+const AGENT: u8 = 1;
+const OPPONENT: u8 = 2;
+const WIN_SCORE: i32 = 1_000_000;
+const DEFAULT_TABLE_BITS: u32 = 18;
+
+/// The built-in player the agent is facing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opponent {
+    Random,
+    /// Alpha-beta negamax searching `depth` plies ahead, with a window-count
+    /// heuristic at the horizon. A depth of 42 or more searches to the end of
+    /// the game and plays perfectly, but is only practical once the board has
+    /// filled up.
+    Minimax { depth: usize },
+}
+
+/// Connect Four on the standard 7x6 board. The agent plays first.
+///
+/// There are about 4.5 * 10^12 reachable positions, far more than a tabular
+/// learner can store, so `state_id` is a hash of the exact position:
+///
+/// 1. The board is packed into the usual 49-bit key (`own stones + mask`,
+///    one sentinel bit per column), which is unique for every position.
+/// 2. The key is spread with a multiplicative (Fibonacci) hash and its top
+///    `table_bits` bits are used as the state id.
+///
+/// Collisions are not resolved: positions landing in the same bucket share
+/// their table entry, which acts as a coarse form of function approximation.
+/// The hash is stateless, so ids are stable across clones, runs and saved
+/// models. Pick `table_bits` so that `2^table_bits` comfortably exceeds the
+/// number of positions visited during training; `exact_key` gives the
+/// collision-free key when it is needed.
+#[derive(Clone)]
+pub struct ConnectFour {
+    board: [[u8; HEIGHT]; WIDTH],  // board[column][row], row 0 at the bottom
+    heights: [usize; WIDTH],
+    moves: usize,
+    winner: Option<u8>,
+    opponent: Opponent,
+    table_bits: u32,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl ConnectFour {
+    pub fn new() -> Self {
+        Self::new_with_opponent(Opponent::Random)
+    }
+
+    pub fn new_with_opponent(opponent: Opponent) -> Self {
+        Self::with_table_bits(opponent, DEFAULT_TABLE_BITS)
+    }
+
+    pub fn with_table_bits(opponent: Opponent, table_bits: u32) -> Self {
+        assert!((1..=32).contains(&table_bits), "table_bits must be between 1 and 32");
+        ConnectFour {
+            board: [[EMPTY; HEIGHT]; WIDTH],
+            heights: [0; WIDTH],
+            moves: 0,
+            winner: None,
+            opponent,
+            table_bits,
+            rng: Xoshiro256PlusPlus::seed_from_u64(42),
+        }
+    }
+
+    /// Unique 49-bit key of the position seen by `player`.
+    fn key(&self, player: u8) -> u64 {
+        let mut own = 0u64;
+        let mut mask = 0u64;
+        for col in 0..WIDTH {
+            for row in 0..self.heights[col] {
+                let bit = 1u64 << (col * (HEIGHT + 1) + row);
+                mask |= bit;
+                if self.board[col][row] == player {
+                    own |= bit;
+                }
+            }
+        }
+        // Adding the bottom row turns the mask into a sentinel above each column
+        let bottom = (0..WIDTH).fold(0u64, |acc, col| acc | 1u64 << (col * (HEIGHT + 1)));
+        own + mask + bottom
+    }
+
+    /// Collision-free key of the current position from the agent's point of view.
+    pub fn exact_key(&self) -> u64 {
+        self.key(AGENT)
+    }
+
+    fn hash_key(&self, key: u64) -> usize {
+        (key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - self.table_bits)) as usize
+    }
+
+    fn legal_moves(&self) -> Vec<usize> {
+        (0..WIDTH).filter(|&col| self.heights[col] < HEIGHT).collect()
+    }
+
+    fn play(&mut self, col: usize, player: u8) {
+        self.board[col][self.heights[col]] = player;
+        self.heights[col] += 1;
+        self.moves += 1;
+    }
+
+    fn undo(&mut self, col: usize) {
+        self.heights[col] -= 1;
+        self.board[col][self.heights[col]] = EMPTY;
+        self.moves -= 1;
+    }
+
+    fn cell(&self, col: isize, row: isize) -> Option<u8> {
+        if (0..WIDTH as isize).contains(&col) && (0..HEIGHT as isize).contains(&row) {
+            Some(self.board[col as usize][row as usize])
+        } else {
+            None
+        }
+    }
+
+    // Checks whether the last stone played in `col` completes a line of four
+    fn is_winning_move(&self, col: usize) -> bool {
+        let row = self.heights[col] - 1;
+        let player = self.board[col][row];
+        let (col, row) = (col as isize, row as isize);
+
+        [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|&(dc, dr)| {
+            let count_dir = |sign: isize| {
+                (1..4)
+                    .take_while(|&k| self.cell(col + sign * k * dc, row + sign * k * dr) == Some(player))
+                    .count()
+            };
+            1 + count_dir(1) + count_dir(-1) >= 4
+        })
+    }
+
+    // Heuristic value of the board for `player`, based on open windows of four
+    fn evaluate(&self, player: u8) -> i32 {
+        let other = if player == AGENT { OPPONENT } else { AGENT };
+        let mut score = 0;
+
+        for col in 0..WIDTH as isize {
+            for row in 0..HEIGHT as isize {
+                for &(dc, dr) in &[(1, 0), (0, 1), (1, 1), (1, -1)] {
+                    let window: Option<Vec<u8>> = (0..4)
+                        .map(|k| self.cell(col + k * dc, row + k * dr))
+                        .collect();
+                    let Some(window) = window else { continue };
+                    let own = window.iter().filter(|&&c| c == player).count();
+                    let theirs = window.iter().filter(|&&c| c == other).count();
+                    score += match (own, theirs) {
+                        (3, 0) => 5,
+                        (2, 0) => 2,
+                        (0, 3) => -4,
+                        _ => 0,
+                    };
+                }
+            }
+        }
+
+        score
+    }
+
+    fn negamax(&mut self, player: u8, depth: usize, mut alpha: i32, beta: i32) -> i32 {
+        let other = if player == AGENT { OPPONENT } else { AGENT };
+        let moves = self.ordered_moves();
+        if moves.is_empty() {
+            return 0;  // Draw
+        }
+        if depth == 0 {
+            return self.evaluate(player);
+        }
+
+        let mut best = i32::MIN + 1;
+        for col in moves {
+            self.play(col, player);
+            let value = if self.is_winning_move(col) {
+                // Prefer faster wins
+                WIN_SCORE + depth as i32
+            } else {
+                -self.negamax(other, depth - 1, -beta, -alpha)
+            };
+            self.undo(col);
+
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    // Central columns first, which makes alpha-beta prune much more
+    fn ordered_moves(&self) -> Vec<usize> {
+        let mut moves = self.legal_moves();
+        moves.sort_by_key(|&col| (col as isize - (WIDTH / 2) as isize).abs());
+        moves
+    }
+
+    fn minimax_move(&mut self, depth: usize) -> usize {
+        let mut best_moves = Vec::new();
+        let mut best_value = i32::MIN;
+
+        for col in self.ordered_moves() {
+            self.play(col, OPPONENT);
+            let value = if self.is_winning_move(col) {
+                WIN_SCORE + depth as i32
+            } else if depth == 0 {
+                self.evaluate(OPPONENT)
+            } else {
+                -self.negamax(AGENT, depth - 1, i32::MIN + 1, i32::MAX)
+            };
+            self.undo(col);
+
+            if value > best_value {
+                best_value = value;
+                best_moves.clear();
+            }
+            if value == best_value {
+                best_moves.push(col);
+            }
+        }
+
+        *best_moves.choose(&mut self.rng).unwrap()
+    }
+
+    fn get_opponent_move(&mut self) -> usize {
+        match self.opponent {
+            Opponent::Random => *self.legal_moves().choose(&mut self.rng).unwrap(),
+            Opponent::Minimax { depth } => self.minimax_move(depth),
+        }
+    }
+
+    fn play_and_check(&mut self, col: usize, player: u8) {
+        self.play(col, player);
+        if self.is_winning_move(col) {
+            self.winner = Some(player);
+        } else if self.moves == WIDTH * HEIGHT {
+            self.winner = Some(EMPTY);  // Draw
+        }
+    }
+}
+
+impl Default for ConnectFour {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for ConnectFour {
+    fn num_states(&self) -> usize {
+        1 << self.table_bits
+    }
+
+    fn num_actions(&self) -> usize {
+        WIDTH
+    }
+
+    fn state_id(&self) -> usize {
+        self.hash_key(self.key(AGENT))
+    }
+
+    fn reset(&mut self) {
+        self.board = [[EMPTY; HEIGHT]; WIDTH];
+        self.heights = [0; WIDTH];
+        self.moves = 0;
+        self.winner = None;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            self.legal_moves()
+        }
+    }
+
+    fn score(&self) -> f32 {
+        match self.winner {
+            Some(AGENT) => 1.0,
+            Some(OPPONENT) => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        self.play_and_check(action, AGENT);
+        if !self.is_game_over() {
+            let opponent_move = self.get_opponent_move();
+            self.play_and_check(opponent_move, OPPONENT);
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        for row in (0..HEIGHT).rev() {
+            for col in 0..WIDTH {
+                match self.board[col][row] {
+                    AGENT => print!("X "),
+                    OPPONENT => print!("O "),
+                    _ => print!(". "),
+                }
+            }
+            println!();
+        }
+        for col in 0..WIDTH {
+            print!("{} ", col);
+        }
+        println!();
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_distinguish_move_orders_and_owners() {
+        let mut a = ConnectFour::new();
+        a.play(0, AGENT);
+        a.play(1, OPPONENT);
+        let mut b = ConnectFour::new();
+        b.play(1, AGENT);
+        b.play(0, OPPONENT);
+        assert_ne!(a.exact_key(), b.exact_key());
+
+        let mut c = ConnectFour::new();
+        c.play(1, OPPONENT);
+        c.play(0, AGENT);
+        assert_eq!(a.exact_key(), c.exact_key());
+        assert_eq!(a.state_id(), c.state_id());
+        assert!(a.state_id() < a.num_states());
+    }
+
+    #[test]
+    fn test_vertical_and_diagonal_wins() {
+        let mut env = ConnectFour::new();
+        for _ in 0..4 {
+            env.play(3, AGENT);
+        }
+        assert!(env.is_winning_move(3));
+
+        let mut env = ConnectFour::new();
+        for col in 0..4 {
+            for _ in 0..col {
+                env.play(col, OPPONENT);
+            }
+            env.play(col, AGENT);
+        }
+        assert!(env.is_winning_move(3));
+    }
+
+    #[test]
+    fn test_minimax_takes_win_and_blocks() {
+        let mut env = ConnectFour::new_with_opponent(Opponent::Minimax { depth: 4 });
+        for col in 0..3 {
+            env.play(col, AGENT);
+        }
+        assert_eq!(env.minimax_move(4), 3);
+
+        for _ in 0..3 {
+            env.play(6, OPPONENT);
+        }
+        assert_eq!(env.minimax_move(4), 6);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Environment;
+use crate::{Environment, EnvError};
 
 #[derive(Clone)]
 pub struct GridWorld {
@@ -7,15 +7,23 @@ pub struct GridWorld {
     size: usize,
 }
 
-impl Environment for GridWorld {
-    fn new() -> Self {
+impl GridWorld {
+    pub fn new() -> Self {
         GridWorld {
             pos_x: 1,
             pos_y: 1,
             size: 4,  // 4x4 grid
         }
     }
+}
+
+impl Default for GridWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+impl Environment for GridWorld {
     fn num_states(&self) -> usize {
         self.size * self.size
     }
@@ -75,13 +83,8 @@ impl Environment for GridWorld {
         }
     }
 
-    fn step(&mut self, action: usize) {
-        if self.is_game_over() {
-            panic!("We are trying to play but game is over!");
-        }
-        if !self.available_actions().contains(&action) {
-            panic!("Unauthorized action!");
-        }
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
 
         match action {
             0 => self.pos_y -= 1,  // Up
@@ -90,6 +93,7 @@ impl Environment for GridWorld {
             3 => self.pos_x -= 1,  // Left
             _ => unreachable!(),
         }
+        Ok(())
     }
 
     fn display(&self) {
@@ -110,6 +114,21 @@ impl Environment for GridWorld {
         }
     }
 
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
+    // Column then row
+    fn observation(&self) -> Vec<f32> {
+        vec![self.pos_x as f32, self.pos_y as f32]
+    }
+
     fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
         let mut probs = vec![vec![vec![0.0; self.num_states()]; self.num_actions()]; self.num_states()];
 
@@ -118,7 +137,7 @@ impl Environment for GridWorld {
                 let state = y * self.size + x;
 
                 // For each state, calculate transitions for each action
-                for action in 0..4 {
+                for (action, next_probs) in probs[state].iter_mut().enumerate() {
                     // Check if action is valid in this state
                     let mut next_x = x;
                     let mut next_y = y;
@@ -132,7 +151,7 @@ impl Environment for GridWorld {
                     }
 
                     let next_state = next_y * self.size + next_x;
-                    probs[state][action][next_state] = 1.0;  // Deterministic transition
+                    next_probs[next_state] = 1.0;  // Deterministic transition
                 }
             }
         }
@@ -148,7 +167,7 @@ impl Environment for GridWorld {
                 let state = y * self.size + x;
 
                 // Define rewards for actions from this state
-                for action in 0..4 {
+                for (action, reward) in rewards[state].iter_mut().enumerate() {
                     let mut next_x = x;
                     let mut next_y = y;
 
@@ -162,11 +181,11 @@ impl Environment for GridWorld {
 
                     // Goal state (top-left corner)
                     if next_x == 0 && next_y == 0 {
-                        rewards[state][action] = 1.0;
+                        *reward = 1.0;
                     }
                     // Negative goal state (bottom-right corner)
                     else if next_x == self.size - 1 && next_y == self.size - 1 {
-                        rewards[state][action] = -1.0;
+                        *reward = -1.0;
                     }
                 }
             }
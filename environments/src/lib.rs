@@ -2,21 +2,118 @@ pub mod line_world;
 pub mod grid_world;
 pub mod rps;
 pub mod secret_env;
+pub mod plugin;
 pub mod monty_hall_paradox1;
 pub mod monty_hall_paradox2;
+pub mod blackjack;
+pub mod tic_tac_toe;
+pub mod connect_four;
+pub mod taxi;
+pub mod random_walk;
+pub mod bandit;
+pub mod monty_hall;
+pub mod belief;
+pub mod remote;
+pub mod vec_env;
+pub mod wrappers;
+pub mod mountain_car;
+pub mod cart_pole;
+
+use std::fmt;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+
+/// A trained policy used as an opponent: (state_id, available_actions) -> action.
+pub type PolicyFn = Arc<dyn Fn(usize, &[usize]) -> usize + Send + Sync>;
+
+/// Why an environment refused to take a step.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EnvError {
+    /// The episode is over and the environment must be reset first.
+    GameOver,
+    /// The action is not one of the currently available actions.
+    IllegalAction { action: usize, legal: Vec<usize> },
+    /// The implementation behind the environment failed, e.g. a native library.
+    Backend(String),
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvError::GameOver => write!(f, "We are trying to play but game is over!"),
+            EnvError::IllegalAction { action, legal } => {
+                write!(f, "Unauthorized action {}! Legal actions are {:?}", action, legal)
+            }
+            EnvError::Backend(message) => write!(f, "Environment backend error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for EnvError {}
 
 pub trait Environment {
-    fn new() -> Self;
     fn num_states(&self) -> usize;
     fn num_actions(&self) -> usize;
     fn state_id(&self) -> usize;
     fn reset(&mut self);
     fn is_game_over(&self) -> bool;
+    /// Actions that can be played now. Empty once the episode has
+    /// terminated; a truncated episode keeps the actions of its last state,
+    /// which learners bootstrap from, although `step` refuses them.
     fn available_actions(&self) -> Vec<usize>;
     fn score(&self) -> f32;
-    fn step(&mut self, action: usize);
+    fn step(&mut self, action: usize) -> Result<(), EnvError>;
     fn display(&self);
 
+    /// Whether the episode was cut short by a step limit rather than ending
+    /// in a terminal state, which learners and shaping must not treat as
+    /// terminal.
+    fn truncated(&self) -> bool {
+        false
+    }
+
+    /// Whether the episode ended in a terminal state, worth 0 from then on.
+    /// Learners bootstrap from the last state of any other finished episode.
+    fn terminated(&self) -> bool {
+        self.is_game_over() && !self.truncated()
+    }
+
+    /// Features of the current state for function approximation. Defaults
+    /// to a one-hot encoding of `state_id`; environments with a geometry
+    /// override it with coordinates, positions and the like.
+    fn observation(&self) -> Vec<f32> {
+        let mut observation = vec![0.0; self.num_states()];
+        observation[self.state_id()] = 1.0;
+        observation
+    }
+
+    /// A new episode of the same environment, independent of the current one.
+    /// Environments whose state is plain data reset a `clone`.
+    fn fresh(&self) -> Self where Self: Sized;
+
+    /// Moves the random number generator to its `stream`-th independent
+    /// stream, so that copies of a seeded environment play different
+    /// episodes. Deterministic environments have nothing to split.
+    fn split_rng(&mut self, _stream: usize) {}
+
+    /// A copy of the current state: stepping it leaves `self` untouched, which
+    /// is what lookahead and tree search need. Fails when the environment
+    /// cannot reproduce its current state, which is why environments backed
+    /// by a library or a server implement this instead of `Clone`.
+    fn snapshot(&self) -> Result<Self, EnvError> where Self: Sized;
+
+    /// Checks that `action` can be played now, before any state is changed.
+    fn check_action(&self, action: usize) -> Result<(), EnvError> {
+        if self.is_game_over() {
+            return Err(EnvError::GameOver);
+        }
+        let legal = self.available_actions();
+        if !legal.contains(&action) {
+            return Err(EnvError::IllegalAction { action, legal });
+        }
+        Ok(())
+    }
+
     //méthodes pour PolicyIteration
     fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
         vec![vec![vec![0.0; self.num_states()]; self.num_actions()]; self.num_states()]
@@ -24,15 +121,15 @@ pub trait Environment {
     fn reward_function(&self) -> Vec<Vec<f32>> {
         vec![vec![0.0; self.num_actions()]; self.num_states()]
     }
-    fn run_policy(&mut self, policy: &[usize]) -> f32 {
+    fn run_policy(&mut self, policy: &[usize]) -> Result<f32, EnvError> {
         self.reset();
         let mut total_reward = 0.0;
         while !self.is_game_over() {
             let state = self.state_id();
             let action = policy[state];
-            self.step(action);
+            self.step(action)?;
             total_reward += self.score();
         }
-        total_reward
+        Ok(total_reward)
     }
 }
\ No newline at end of file
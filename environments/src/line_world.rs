@@ -1,15 +1,23 @@
-use crate::Environment;
+use crate::{Environment, EnvError};
 
 #[derive(Clone)]
 pub struct LineWorld {
     pos: usize,
 }
 
-impl Environment for LineWorld {
-    fn new() -> Self {
+impl LineWorld {
+    pub fn new() -> Self {
         LineWorld { pos: 2 }
     }
+}
+
+impl Default for LineWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+impl Environment for LineWorld {
     fn num_states(&self) -> usize {
         5
     }
@@ -46,13 +54,8 @@ impl Environment for LineWorld {
         }
     }
 
-    fn step(&mut self, action: usize) {
-        if self.is_game_over() {
-            panic!("We are trying to play but game is over!");
-        }
-        if !self.available_actions().contains(&action) {
-            panic!("Unauthorized action!");
-        }
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
         match action {
             0 => {
                 self.pos -= 1;
@@ -62,6 +65,7 @@ impl Environment for LineWorld {
             }
             _ => unreachable!(),
         }
+        Ok(())
     }
 
     fn display(&self) {
@@ -74,4 +78,18 @@ impl Environment for LineWorld {
         }
         println!();
     }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        vec![self.pos as f32]
+    }
 }
@@ -0,0 +1,383 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+/// One host turn: the door the player held when the host acted, and the
+/// bitmask of the doors the host opened.
+type Turn = (usize, u32);
+
+/// Exact MDP of a Monty Hall configuration, shared by all clones.
+struct Model {
+    histories: Vec<Vec<Turn>>,
+    ids: HashMap<Vec<Turn>, usize>,
+    // [state][action] -> (next state, probability), sparse
+    transitions: Vec<Vec<Vec<(usize, f32)>>>,
+    rewards: Vec<Vec<f32>>,
+}
+
+/// Monty Hall with `num_doors` doors, `opened_per_stage` doors opened by the
+/// host before each of `switch_stages` decisions.
+///
+/// The player first picks a door (any of the `num_doors` actions). Before
+/// every switch stage the host opens `opened_per_stage` doors that are neither
+/// held by the player nor hiding the prize, choosing uniformly among the valid
+/// sets; the player then picks any closed door, keeping the current one being
+/// "stay". The reward is 1 if the final door hides the prize and 0 otherwise.
+///
+/// The state is the full observable history (doors held and doors opened at
+/// each host turn), which is what the posterior over the prize depends on, so
+/// the process is Markov without exposing the prize. State ids are assigned by
+/// enumerating every reachable history, plus one terminal state at the end.
+/// The enumeration grows quickly with the number of stages: it is meant for
+/// small configurations such as the classic 3-door game or 5 doors with 2 stages.
+#[derive(Clone)]
+pub struct MontyHallN {
+    num_doors: usize,
+    opened_per_stage: usize,
+    switch_stages: usize,
+    winning_door: usize,
+    history: Vec<Turn>,
+    final_choice: Option<usize>,
+    model: Arc<Model>,
+    rng: Xoshiro256PlusPlus,
+}
+
+fn binomial(n: usize, k: usize) -> f32 {
+    if k > n {
+        return 0.0;
+    }
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f32 / (i + 1) as f32)
+}
+
+// Every bitmask with exactly k of the given doors set
+fn subsets(doors: &[usize], k: usize) -> Vec<u32> {
+    if k == 0 {
+        return vec![0];
+    }
+    if doors.len() < k {
+        return vec![];
+    }
+    let (first, rest) = (doors[0], &doors[1..]);
+    let mut result: Vec<u32> = subsets(rest, k - 1).into_iter().map(|m| m | 1 << first).collect();
+    result.extend(subsets(rest, k));
+    result
+}
+
+impl MontyHallN {
+    pub fn new() -> Self {
+        Self::with_config(3, 1, 1)
+    }
+
+    pub fn with_config(num_doors: usize, opened_per_stage: usize, switch_stages: usize) -> Self {
+        assert!(num_doors <= 16, "At most 16 doors are supported");
+        assert!(opened_per_stage >= 1 && switch_stages >= 1, "The host must open doors at least once");
+        assert!(
+            num_doors >= switch_stages * opened_per_stage + 2,
+            "The host needs a closed door that is neither held nor winning at every stage"
+        );
+
+        let model = Self::build_model(num_doors, opened_per_stage, switch_stages);
+        let mut env = MontyHallN {
+            num_doors,
+            opened_per_stage,
+            switch_stages,
+            winning_door: 0,
+            history: Vec::new(),
+            final_choice: None,
+            model: Arc::new(model),
+            rng: Xoshiro256PlusPlus::seed_from_u64(42),
+        };
+        env.reset();
+        env
+    }
+
+    /// Probability of winning by always keeping the first door.
+    pub fn stay_win_probability(&self) -> f32 {
+        1.0 / self.num_doors as f32
+    }
+
+    /// Probability of winning by keeping the first door until the last stage
+    /// and then switching to one of the other closed doors, which is optimal.
+    pub fn switch_win_probability(&self) -> f32 {
+        let n = self.num_doors as f32;
+        let closed_others = (self.num_doors - self.switch_stages * self.opened_per_stage - 1) as f32;
+        (n - 1.0) / (n * closed_others)
+    }
+
+    fn opened_mask(history: &[Turn]) -> u32 {
+        history.iter().fold(0, |acc, &(_, opened)| acc | opened)
+    }
+
+    fn closed_doors(&self) -> Vec<usize> {
+        let opened = Self::opened_mask(&self.history);
+        (0..self.num_doors).filter(|&d| opened & (1 << d) == 0).collect()
+    }
+
+    /// Posterior over the winning door given the observed host turns.
+    fn posterior(history: &[Turn], num_doors: usize, opened_per_stage: usize) -> Vec<f32> {
+        let mut probs = vec![1.0 / num_doors as f32; num_doors];
+        let mut opened = 0u32;
+
+        for &(held, newly_opened) in history {
+            for (door, p) in probs.iter_mut().enumerate() {
+                if *p == 0.0 {
+                    continue;
+                }
+                let eligible = (0..num_doors)
+                    .filter(|&d| opened & (1 << d) == 0 && d != held && d != door)
+                    .fold(0u32, |acc, d| acc | 1 << d);
+                if newly_opened & !eligible != 0 {
+                    *p = 0.0;
+                } else {
+                    *p /= binomial(eligible.count_ones() as usize, opened_per_stage);
+                }
+            }
+            opened |= newly_opened;
+        }
+
+        let total: f32 = probs.iter().sum();
+        probs.iter().map(|p| p / total).collect()
+    }
+
+    fn build_model(num_doors: usize, opened_per_stage: usize, switch_stages: usize) -> Model {
+        let mut histories: Vec<Vec<Turn>> = vec![Vec::new()];
+        let mut ids = HashMap::new();
+        ids.insert(Vec::new(), 0);
+        let mut transitions = Vec::new();
+        let mut rewards = Vec::new();
+        let mut queue = VecDeque::from([0usize]);
+
+        // Histories are discovered in breadth-first order, so the queue
+        // visits states in id order and `transitions[state]` lines up
+        while let Some(state) = queue.pop_front() {
+            let history = histories[state].clone();
+            let posterior = Self::posterior(&history, num_doors, opened_per_stage);
+            let opened = Self::opened_mask(&history);
+            let mut state_transitions = vec![Vec::new(); num_doors];
+            let mut state_rewards = vec![0.0; num_doors];
+
+            for held in (0..num_doors).filter(|&d| opened & (1 << d) == 0) {
+                if history.len() == switch_stages {
+                    // Final decision: the episode ends and pays off
+                    state_rewards[held] = posterior[held];
+                    continue;
+                }
+
+                let mut next: HashMap<u32, f32> = HashMap::new();
+                for (door, &p) in posterior.iter().enumerate() {
+                    if p == 0.0 {
+                        continue;
+                    }
+                    let eligible: Vec<usize> = (0..num_doors)
+                        .filter(|&d| opened & (1 << d) == 0 && d != held && d != door)
+                        .collect();
+                    let choices = subsets(&eligible, opened_per_stage);
+                    for mask in &choices {
+                        *next.entry(*mask).or_insert(0.0) += p / choices.len() as f32;
+                    }
+                }
+
+                let mut masks: Vec<(u32, f32)> = next.into_iter().collect();
+                masks.sort_by_key(|&(mask, _)| mask);
+                for (mask, p) in masks {
+                    let mut next_history = history.clone();
+                    next_history.push((held, mask));
+                    let next_state = *ids.entry(next_history.clone()).or_insert_with(|| {
+                        histories.push(next_history);
+                        queue.push_back(histories.len() - 1);
+                        histories.len() - 1
+                    });
+                    state_transitions[held].push((next_state, p));
+                }
+            }
+
+            transitions.push(state_transitions);
+            rewards.push(state_rewards);
+        }
+
+        // Final decisions lead to the terminal state, which comes last
+        let terminal = histories.len();
+        for (state, history) in histories.iter().enumerate() {
+            if history.len() == switch_stages {
+                let opened = Self::opened_mask(history);
+                for held in (0..num_doors).filter(|&d| opened & (1 << d) == 0) {
+                    transitions[state][held].push((terminal, 1.0));
+                }
+            }
+        }
+        transitions.push(vec![Vec::new(); num_doors]);
+        rewards.push(vec![0.0; num_doors]);
+
+        Model { histories, ids, transitions, rewards }
+    }
+}
+
+impl Default for MontyHallN {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for MontyHallN {
+    fn num_states(&self) -> usize {
+        self.model.histories.len() + 1
+    }
+
+    fn num_actions(&self) -> usize {
+        self.num_doors
+    }
+
+    fn state_id(&self) -> usize {
+        if self.final_choice.is_some() {
+            return self.model.histories.len();
+        }
+        self.model.ids[&self.history]
+    }
+
+    fn reset(&mut self) {
+        self.winning_door = self.rng.random_range(0..self.num_doors);
+        self.history.clear();
+        self.final_choice = None;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.final_choice.is_some()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            self.closed_doors()
+        }
+    }
+
+    fn score(&self) -> f32 {
+        match self.final_choice {
+            Some(door) if door == self.winning_door => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        if self.history.len() == self.switch_stages {
+            self.final_choice = Some(action);
+            return Ok(());
+        }
+
+        let mut eligible: Vec<usize> = self.closed_doors()
+            .into_iter()
+            .filter(|&d| d != action && d != self.winning_door)
+            .collect();
+        eligible.shuffle(&mut self.rng);
+        let opened = eligible[..self.opened_per_stage]
+            .iter()
+            .fold(0u32, |acc, &d| acc | 1 << d);
+        self.history.push((action, opened));
+        Ok(())
+    }
+
+    fn display(&self) {
+        let opened = Self::opened_mask(&self.history);
+        let held = self.final_choice.or(self.history.last().map(|&(held, _)| held));
+        for door in 0..self.num_doors {
+            if opened & (1 << door) != 0 {
+                print!("[ x ]");
+            } else if Some(door) == held {
+                print!("[>{}<]", door);
+            } else {
+                print!("[ {} ]", door);
+            }
+        }
+        println!();
+        if self.is_game_over() {
+            println!("Winning door: {}", self.winning_door);
+        }
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        let mut probs = vec![vec![vec![0.0; self.num_states()]; self.num_actions()]; self.num_states()];
+        for (state, state_transitions) in self.model.transitions.iter().enumerate() {
+            for (action, next) in state_transitions.iter().enumerate() {
+                for &(next_state, p) in next {
+                    probs[state][action][next_state] = p;
+                }
+            }
+        }
+        probs
+    }
+
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        self.model.rewards.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classic_game_states() {
+        let env = MontyHallN::new();
+        // Root, 3 choices x 2 possible reveals, terminal
+        assert_eq!(env.num_states(), 1 + 6 + 1);
+        let probs = env.transition_probabilities();
+        for state_probs in &probs {
+            for next_probs in state_probs {
+                let total: f32 = next_probs.iter().sum();
+                assert!(total == 0.0 || (total - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_posterior_favours_the_other_door() {
+        // Player holds door 0 and the host opened door 1
+        let posterior = MontyHallN::posterior(&[(0, 1 << 1)], 3, 1);
+        assert!((posterior[0] - 1.0 / 3.0).abs() < 1e-5);
+        assert_eq!(posterior[1], 0.0);
+        assert!((posterior[2] - 2.0 / 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_simulated_win_rates_match_analytic_values() {
+        let mut env = MontyHallN::with_config(5, 1, 2);
+        let episodes = 20000;
+        let (mut stay_wins, mut switch_wins) = (0.0, 0.0);
+
+        for _ in 0..episodes {
+            env.reset();
+            env.step(0).unwrap();
+            env.step(0).unwrap();
+            let other = env.available_actions().into_iter().find(|&d| d != 0).unwrap();
+            let mut switched = env.snapshot().unwrap();
+            env.step(0).unwrap();
+            switched.step(other).unwrap();
+            stay_wins += env.score();
+            switch_wins += switched.score();
+        }
+
+        assert!((stay_wins / episodes as f32 - env.stay_win_probability()).abs() < 0.02);
+        assert!((switch_wins / episodes as f32 - env.switch_win_probability()).abs() < 0.02);
+    }
+}
@@ -1,27 +1,180 @@
-use rand::Rng;
-use crate::Environment;
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+use crate::belief::PartiallyObservable;
 
+const DEFAULT_SEED: u64 = 42;
+
+/// What `state_id` reveals to the agent in the Monty Hall games.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    /// Only the first pick and the door the host opened. The winning door is
+    /// hidden, so the models average over it.
+    ObservationOnly,
+    /// The winning door as well, the default. This makes the game a leaky
+    /// MDP where the agent can learn where the prize is instead of when to
+    /// switch.
+    FullState,
+}
+
+/// Probability that the host opens `revealed` when the player holds `chosen`
+/// and the prize is behind `winning`: uniform over the other losing doors.
+pub(crate) fn reveal_probability(num_doors: usize, winning: usize, chosen: usize, revealed: usize) -> f32 {
+    if revealed == chosen || revealed == winning {
+        return 0.0;
+    }
+    let candidates = if chosen == winning { num_doors - 1 } else { num_doors - 2 };
+    1.0 / candidates as f32
+}
+
+/// Number of observation ids: 0 before the first pick, `chosen * n + revealed`
+/// after the reveal and `n * n` once the game is over.
+pub(crate) fn observation_count(num_doors: usize) -> usize {
+    num_doors * num_doors + 1
+}
+
+pub(crate) fn encode_state(
+    num_doors: usize,
+    visibility: Visibility,
+    winning: usize,
+    observation: usize,
+) -> usize {
+    match visibility {
+        Visibility::ObservationOnly => observation,
+        Visibility::FullState => winning * observation_count(num_doors) + observation,
+    }
+}
+
+/// Transition and reward models of a one-reveal Monty Hall game, where
+/// action 0 keeps the first pick and action 1 moves to `switch_target`.
+///
+/// The winning door is a chance node drawn at reset: in `ObservationOnly`
+/// every quantity is averaged over it, in `FullState` each block of
+/// observation ids is conditioned on one winning door.
+pub(crate) fn build_models(
+    num_doors: usize,
+    visibility: Visibility,
+    switch_target: impl Fn(usize, usize) -> usize,
+) -> (Vec<Vec<Vec<f32>>>, Vec<Vec<f32>>) {
+    let blocks = match visibility {
+        Visibility::ObservationOnly => 1,
+        Visibility::FullState => num_doors,
+    };
+    let num_states = blocks * observation_count(num_doors);
+    let mut probs = vec![vec![vec![0.0; num_states]; num_doors]; num_states];
+    let mut rewards = vec![vec![0.0; num_doors]; num_states];
+
+    for block in 0..blocks {
+        // Prior weight of each winning door within this block
+        let weight = |winning: usize| match visibility {
+            Visibility::ObservationOnly => 1.0 / num_doors as f32,
+            Visibility::FullState => if winning == block { 1.0 } else { 0.0 },
+        };
+        let start = encode_state(num_doors, visibility, block, 0);
+        let terminal = encode_state(num_doors, visibility, block, num_doors * num_doors);
+
+        for chosen in 0..num_doors {
+            for revealed in (0..num_doors).filter(|&r| r != chosen) {
+                let decision = encode_state(num_doors, visibility, block, chosen * num_doors + revealed);
+                let joint: Vec<f32> = (0..num_doors)
+                    .map(|winning| weight(winning) * reveal_probability(num_doors, winning, chosen, revealed))
+                    .collect();
+                let total: f32 = joint.iter().sum();
+
+                // The host's reveal, averaged over where the prize may be
+                probs[start][chosen][decision] = total;
+                if total == 0.0 {
+                    continue;
+                }
+
+                // Keeping or switching ends the game and pays the posterior
+                probs[decision][0][terminal] = 1.0;
+                probs[decision][1][terminal] = 1.0;
+                rewards[decision][0] = joint[chosen] / total;
+                rewards[decision][1] = joint[switch_target(chosen, revealed)] / total;
+            }
+        }
+    }
+    (probs, rewards)
+}
+
+/// Likelihood of moving between two state ids when the prize is behind `hidden`.
+pub(crate) fn observation_probability(
+    num_doors: usize,
+    visibility: Visibility,
+    hidden: usize,
+    state: usize,
+    action: usize,
+    next: usize,
+) -> f32 {
+    let count = observation_count(num_doors);
+    if visibility == Visibility::FullState && next / count != hidden {
+        return 0.0;
+    }
+    match (state % count, next % count) {
+        (0, observation) if observation > 0 && observation < num_doors * num_doors => {
+            let (chosen, revealed) = (observation / num_doors, observation % num_doors);
+            if chosen == action { reveal_probability(num_doors, hidden, chosen, revealed) } else { 0.0 }
+        }
+        // The final choice is not observed until the score
+        (_, observation) if observation == num_doors * num_doors => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// Classic three-door Monty Hall.
+///
+/// The state is the winning door and what the player has seen: 0 before the
+/// first pick, `chosen * 3 + revealed` once the host has opened a door, and 9
+/// when the game is over, in one block of 10 ids per winning door. With
+/// `Visibility::ObservationOnly` only the ids of what was seen are left.
 #[derive(Clone)]
 pub struct MontyHall {
     winning_door: usize,
     chosen_door: Option<usize>,
     revealed_door: Option<usize>,
     final_choice: Option<usize>,
+    visibility: Visibility,
+    rng: Xoshiro256PlusPlus,
 }
 
-impl Environment for MontyHall {
-    fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        MontyHall {
-            winning_door: rng.gen_range(0..3),
+impl MontyHall {
+    pub fn new() -> Self {
+        Self::new_with_visibility(Visibility::FullState)
+    }
+
+    pub fn new_with_visibility(visibility: Visibility) -> Self {
+        Self::with_seed(visibility, DEFAULT_SEED)
+    }
+
+    pub fn with_seed(visibility: Visibility, seed: u64) -> Self {
+        let mut env = MontyHall {
+            winning_door: 0,
             chosen_door: None,
             revealed_door: None,
             final_choice: None,
-        }
+            visibility,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        env.reset();
+        env
     }
 
+    fn switch_target(chosen: usize, revealed: usize) -> usize {
+        3 - chosen - revealed
+    }
+}
+
+impl Default for MontyHall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for MontyHall {
     fn num_states(&self) -> usize {
-        3 * 3 * 3  // 3 portes * 3 choix initiaux * 3 portes révélées
+        let blocks = if self.visibility == Visibility::FullState { 3 } else { 1 };
+        blocks * observation_count(3)  // 3 choix initiaux * 3 portes révélées + état terminal
     }
 
     fn num_actions(&self) -> usize {
@@ -29,14 +182,16 @@ impl Environment for MontyHall {
     }
 
     fn state_id(&self) -> usize {
-        let chosen = self.chosen_door.unwrap_or(0);
-        let revealed = self.revealed_door.unwrap_or(0);
-        self.winning_door * 9 + chosen * 3 + revealed
+        let observation = if self.is_game_over() {
+            9
+        } else {
+            self.chosen_door.unwrap_or(0) * 3 + self.revealed_door.unwrap_or(0)
+        };
+        encode_state(3, self.visibility, self.winning_door, observation)
     }
 
     fn reset(&mut self) {
-        let mut rng = rand::thread_rng();
-        self.winning_door = rng.gen_range(0..3);
+        self.winning_door = self.rng.random_range(0..3);
         self.chosen_door = None;
         self.revealed_door = None;
         self.final_choice = None;
@@ -66,25 +221,22 @@ impl Environment for MontyHall {
         }
     }
 
-    fn step(&mut self, action: usize) {
-        if self.is_game_over() {
-            println!("Partie terminée. Aucun mouvement possible.");
-            return;
-        }
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
 
-        if self.chosen_door.is_none() {
-            self.chosen_door = Some(action);
-            let mut rng = rand::thread_rng();
-            let mut doors = vec![0, 1, 2];
-            doors.retain(|&d| d != action && d != self.winning_door);
-            self.revealed_door = Some(doors[rng.gen_range(0..doors.len())]);
-        } else {
+        if let Some(chosen) = self.chosen_door {
             if action == 1 {
-                self.final_choice = Some(3 - self.chosen_door.unwrap() - self.revealed_door.unwrap());
+                self.final_choice = Some(Self::switch_target(chosen, self.revealed_door.unwrap()));
             } else {
-                self.final_choice = self.chosen_door;
+                self.final_choice = Some(chosen);
             }
+        } else {
+            self.chosen_door = Some(action);
+            let mut doors = vec![0, 1, 2];
+            doors.retain(|&d| d != action && d != self.winning_door);
+            self.revealed_door = Some(doors[self.rng.random_range(0..doors.len())]);
         }
+        Ok(())
     }
 
     fn display(&self) {
@@ -94,27 +246,33 @@ impl Environment for MontyHall {
         println!("Final choice: {:?}", self.final_choice);
     }
 
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
     fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
-        vec![vec![vec![1.0; self.num_states()]; self.num_actions()]; self.num_states()]
+        build_models(3, self.visibility, Self::switch_target).0
     }
 
     fn reward_function(&self) -> Vec<Vec<f32>> {
-        let mut rewards = vec![vec![0.0; self.num_actions()]; self.num_states()];
-        for state in 0..self.num_states() {
-            for action in 0..self.num_actions() {
-
-                if self.is_game_over() {
-                    rewards[state][action] = if self.final_choice == Some(self.winning_door) { 1.0 } else { 0.0 };
-                }
-            }
-        }
-        rewards
+        build_models(3, self.visibility, Self::switch_target).1
     }
 
-    fn run_policy(&mut self, policy: &[usize]) -> f32 {
+    fn run_policy(&mut self, policy: &[usize]) -> Result<f32, EnvError> {
         let mut total_reward = 0.0;
         let mut switch_count = 0;
-        let mut prev_choice = None;
 
         self.reset();
 
@@ -123,8 +281,8 @@ impl Environment for MontyHall {
                 break;
             }
 
-            prev_choice = self.chosen_door;
-            self.step(action);
+            let prev_choice = self.chosen_door;
+            self.step(action)?;
 
 
             println!("Action: {}, Choix initial: {:?}, Choix final: {:?}",
@@ -144,6 +302,16 @@ impl Environment for MontyHall {
 
         println!("L'agent a changé de porte {} fois.", switch_count);
         println!("Récompense totale: {}", total_reward);
-        total_reward
+        Ok(total_reward)
+    }
+}
+
+impl PartiallyObservable for MontyHall {
+    fn num_hidden_states(&self) -> usize {
+        3
+    }
+
+    fn observation_probability(&self, hidden: usize, state: usize, action: usize, next: usize) -> f32 {
+        observation_probability(3, self.visibility, hidden, state, action, next)
     }
 }
@@ -1,6 +1,17 @@
-use rand::Rng;
-use crate::Environment;
-
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+use crate::belief::PartiallyObservable;
+use crate::monty_hall_paradox1::{build_models, encode_state, observation_count, observation_probability, Visibility};
+
+const DEFAULT_SEED: u64 = 42;
+
+/// Five-door Monty Hall: the host opens one losing door and switching moves
+/// to the lowest-numbered door that is neither held nor open.
+///
+/// State ids follow `MontyHall`: 0 before the first pick, `chosen * 5 +
+/// revealed` once the host has opened a door and 25 when the game is over,
+/// in one block per winning door unless `Visibility::ObservationOnly`.
 #[derive(Clone)]
 pub struct MontyHall2 {
     winning_door: usize,
@@ -8,22 +19,48 @@ pub struct MontyHall2 {
     revealed_door: Option<usize>,
     final_choice: Option<usize>,
     actions_taken: usize, // Nombre d'actions effectuées
+    visibility: Visibility,
+    rng: Xoshiro256PlusPlus,
 }
 
-impl Environment for MontyHall2 {
-    fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        MontyHall2 {
-            winning_door: rng.gen_range(0..5), // Choix aléatoire d'une porte parmi 5
+impl MontyHall2 {
+    pub fn new() -> Self {
+        Self::new_with_visibility(Visibility::FullState)
+    }
+
+    pub fn new_with_visibility(visibility: Visibility) -> Self {
+        Self::with_seed(visibility, DEFAULT_SEED)
+    }
+
+    pub fn with_seed(visibility: Visibility, seed: u64) -> Self {
+        let mut env = MontyHall2 {
+            winning_door: 0,
             chosen_door: None,
             revealed_door: None,
             final_choice: None,
             actions_taken: 0,
-        }
+            visibility,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        env.reset();
+        env
     }
 
+    fn switch_target(chosen: usize, revealed: usize) -> usize {
+        (0..5).find(|&d| d != chosen && d != revealed).unwrap()
+    }
+}
+
+impl Default for MontyHall2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for MontyHall2 {
     fn num_states(&self) -> usize {
-        5 * 5 * 5  // 5 portes * 5 choix initiaux * 5 portes révélées
+        let blocks = if self.visibility == Visibility::FullState { 5 } else { 1 };
+        blocks * observation_count(5)  // 5 choix initiaux * 5 portes révélées + état terminal
     }
 
     fn num_actions(&self) -> usize {
@@ -31,14 +68,16 @@ impl Environment for MontyHall2 {
     }
 
     fn state_id(&self) -> usize {
-        let chosen = self.chosen_door.unwrap_or(0);
-        let revealed = self.revealed_door.unwrap_or(0);
-        self.winning_door * 25 + chosen * 5 + revealed
+        let observation = if self.is_game_over() {
+            25
+        } else {
+            self.chosen_door.unwrap_or(0) * 5 + self.revealed_door.unwrap_or(0)
+        };
+        encode_state(5, self.visibility, self.winning_door, observation)
     }
 
     fn reset(&mut self) {
-        let mut rng = rand::thread_rng();
-        self.winning_door = rng.gen_range(0..5);
+        self.winning_door = self.rng.random_range(0..5);
         self.chosen_door = None;
         self.revealed_door = None;
         self.final_choice = None;
@@ -69,32 +108,29 @@ impl Environment for MontyHall2 {
         }
     }
 
-    fn step(&mut self, action: usize) {
-        if self.is_game_over() {
-            println!("Partie terminée. Aucun mouvement possible.");
-            return;
-        }
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
 
-        if self.chosen_door.is_none() {
-            // Premier choix de porte
-            self.chosen_door = Some(action);
-            let mut rng = rand::thread_rng();
-            let mut doors = (0..5).collect::<Vec<_>>();
-            doors.retain(|&d| d != action && d != self.winning_door);
-            self.revealed_door = Some(doors[rng.gen_range(0..doors.len())]);
-        } else {
+        if let Some(chosen) = self.chosen_door {
             if self.actions_taken < 4 {
                 if action == 1 {
                     // Si l'action est de changer de porte
-                    self.final_choice = Some((0..5).filter(|&d| d != self.chosen_door.unwrap() && d != self.revealed_door.unwrap()).next().unwrap());
+                    self.final_choice = Some(Self::switch_target(chosen, self.revealed_door.unwrap()));
                 } else {
                     // Garder la porte choisie initialement
-                    self.final_choice = self.chosen_door;
+                    self.final_choice = Some(chosen);
                 }
             }
+        } else {
+            // Premier choix de porte
+            self.chosen_door = Some(action);
+            let mut doors = (0..5).collect::<Vec<_>>();
+            doors.retain(|&d| d != action && d != self.winning_door);
+            self.revealed_door = Some(doors[self.rng.random_range(0..doors.len())]);
         }
 
         self.actions_taken += 1;
+        Ok(())
     }
 
     fn display(&self) {
@@ -104,27 +140,33 @@ impl Environment for MontyHall2 {
         println!("Final choice: {:?}", self.final_choice);
     }
 
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
     fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
-        vec![vec![vec![1.0; self.num_states()]; self.num_actions()]; self.num_states()]
+        build_models(5, self.visibility, Self::switch_target).0
     }
 
     fn reward_function(&self) -> Vec<Vec<f32>> {
-        let mut rewards = vec![vec![0.0; self.num_actions()]; self.num_states()];
-        for state in 0..self.num_states() {
-            for action in 0..self.num_actions() {
-                // On vérifie si l'action mène à une décision finale et si elle est correcte
-                if self.is_game_over() {
-                    rewards[state][action] = if self.final_choice == Some(self.winning_door) { 1.0 } else { 0.0 };
-                }
-            }
-        }
-        rewards
+        build_models(5, self.visibility, Self::switch_target).1
     }
 
-    fn run_policy(&mut self, policy: &[usize]) -> f32 {
+    fn run_policy(&mut self, policy: &[usize]) -> Result<f32, EnvError> {
         let mut total_reward = 0.0;
         let mut switch_count = 0; // Compteur de changement de porte
-        let mut prev_choice = None;
 
         self.reset();
 
@@ -133,8 +175,8 @@ impl Environment for MontyHall2 {
                 break;
             }
 
-            prev_choice = self.chosen_door; // Sauvegarde le choix précédent
-            self.step(action);
+            let prev_choice = self.chosen_door; // Sauvegarde le choix précédent
+            self.step(action)?;
 
             // Affiche les actions et les choix à chaque étape
             println!("Action: {}, Choix initial: {:?}, Choix final: {:?}",
@@ -155,6 +197,16 @@ impl Environment for MontyHall2 {
 
         println!("L'agent a changé de porte {} fois.", switch_count);
         println!("Récompense totale: {}", total_reward);
-        total_reward
+        Ok(total_reward)
+    }
+}
+
+impl PartiallyObservable for MontyHall2 {
+    fn num_hidden_states(&self) -> usize {
+        5
+    }
+
+    fn observation_probability(&self, hidden: usize, state: usize, action: usize, next: usize) -> f32 {
+        observation_probability(5, self.visibility, hidden, state, action, next)
     }
 }
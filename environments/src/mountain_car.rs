@@ -0,0 +1,198 @@
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+const DEFAULT_SEED: u64 = 42;
+pub const MIN_POSITION: f32 = -1.2;
+pub const MAX_POSITION: f32 = 0.5;
+pub const MAX_SPEED: f32 = 0.07;
+const DEFAULT_MAX_STEPS: usize = 1000;
+const DEFAULT_BINS: usize = 20;
+
+/// Mountain Car as described in Sutton & Barto (Example 10.1).
+///
+/// An underpowered car in a valley must rock back and forth to reach the goal
+/// at the top of the right hill. Actions are full reverse (0), zero throttle
+/// (1) and full forward (2); every step costs 1 until the goal is reached or
+/// the step cap runs out.
+///
+/// `observation` gives the continuous (position, velocity) pair, while
+/// `state_id` cuts both into `bins` intervals for tabular learners.
+#[derive(Clone)]
+pub struct MountainCar {
+    position: f32,
+    velocity: f32,
+    steps: usize,
+    max_steps: usize,
+    bins: usize,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl MountainCar {
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_config(seed, DEFAULT_MAX_STEPS, DEFAULT_BINS)
+    }
+
+    /// Episodes end after `max_steps` steps, and `state_id` uses `bins`
+    /// intervals per dimension.
+    pub fn with_config(seed: u64, max_steps: usize, bins: usize) -> Self {
+        assert!(bins > 0, "Mountain Car needs at least one bin per dimension");
+        let mut env = MountainCar {
+            position: 0.0,
+            velocity: 0.0,
+            steps: 0,
+            max_steps,
+            bins,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        env.reset();
+        env
+    }
+
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    pub fn reached_goal(&self) -> bool {
+        self.position >= MAX_POSITION
+    }
+
+    fn bin(&self, value: f32, low: f32, high: f32) -> usize {
+        (((value - low) / (high - low) * self.bins as f32) as usize).min(self.bins - 1)
+    }
+}
+
+impl Default for MountainCar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for MountainCar {
+    fn num_states(&self) -> usize {
+        self.bins * self.bins
+    }
+
+    fn num_actions(&self) -> usize {
+        3  // Reverse (0), Coast (1), Forward (2)
+    }
+
+    fn state_id(&self) -> usize {
+        self.bin(self.position, MIN_POSITION, MAX_POSITION) * self.bins
+            + self.bin(self.velocity, -MAX_SPEED, MAX_SPEED)
+    }
+
+    fn reset(&mut self) {
+        self.position = self.rng.random_range(-0.6..-0.4);
+        self.velocity = 0.0;
+        self.steps = 0;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.reached_goal() || self.steps >= self.max_steps
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.terminated() {
+            vec![]
+        } else {
+            vec![0, 1, 2]
+        }
+    }
+
+    fn score(&self) -> f32 {
+        -(self.steps as f32)
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        let throttle = action as f32 - 1.0;
+        self.velocity = (self.velocity + 0.001 * throttle - 0.0025 * (3.0 * self.position).cos())
+            .clamp(-MAX_SPEED, MAX_SPEED);
+        self.position = (self.position + self.velocity).clamp(MIN_POSITION, MAX_POSITION);
+        // The left edge is an inelastic wall
+        if self.position == MIN_POSITION {
+            self.velocity = 0.0;
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn display(&self) {
+        let width = 40;
+        let column = ((self.position - MIN_POSITION) / (MAX_POSITION - MIN_POSITION) * (width - 1) as f32) as usize;
+        let track: String = (0..width).map(|i| if i == column { 'C' } else { '_' }).collect();
+        println!("{}|", track);
+        println!("Position: {:.3}, velocity: {:.4}, step {}", self.position, self.velocity, self.steps);
+        if self.reached_goal() {
+            println!("Reached the goal!");
+        }
+    }
+
+    fn truncated(&self) -> bool {
+        self.steps >= self.max_steps && !self.reached_goal()
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        vec![self.position, self.velocity]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pumping_energy_reaches_the_goal() {
+        let mut env = MountainCar::with_seed(7);
+        for _ in 0..10 {
+            env.reset();
+            assert!((-0.6..-0.4).contains(&env.position()));
+            while !env.is_game_over() {
+                assert!(env.state_id() < env.num_states());
+                // Push in the direction of motion
+                let action = if env.velocity() < 0.0 { 0 } else { 2 };
+                env.step(action).unwrap();
+            }
+            assert!(env.reached_goal());
+            assert!(env.score() > -200.0);
+        }
+    }
+
+    #[test]
+    fn test_coasting_stops_at_the_step_cap() {
+        let mut env = MountainCar::with_config(7, 50, 10);
+        assert_eq!(env.num_states(), 100);
+        while !env.is_game_over() {
+            env.step(1).unwrap();
+        }
+        assert!(!env.reached_goal());
+        assert_eq!(env.score(), -50.0);
+        assert_eq!(env.step(1), Err(EnvError::GameOver));
+    }
+}
@@ -0,0 +1,271 @@
+//! Environments loaded at runtime from shared libraries.
+//!
+//! A plugin is a shared library exporting the C functions below. Environments
+//! are opaque pointers created and freed by the plugin; the host never looks
+//! inside them.
+//!
+//! ```c
+//! uint32_t      plugin_env_abi_version(void);  // must return PLUGIN_ABI_VERSION
+//! void*         plugin_env_new(void);          // a new environment, ready to play
+//! void          plugin_env_delete(void* env);
+//! size_t        plugin_env_num_states(void);
+//! size_t        plugin_env_num_actions(void);
+//! size_t        plugin_env_state_id(const void* env);
+//! void          plugin_env_reset(void* env);
+//! bool          plugin_env_is_game_over(const void* env);
+//! const size_t* plugin_env_available_actions(const void* env);
+//! size_t        plugin_env_available_actions_len(const void* env);
+//! void          plugin_env_available_actions_delete(const size_t* actions, size_t len);
+//! float         plugin_env_score(const void* env);
+//! void          plugin_env_step(void* env, size_t action);
+//! void          plugin_env_display(const void* env);
+//! void*         plugin_env_clone(const void* env);  // optional
+//! ```
+//!
+//! The array returned by `available_actions` belongs to the caller until it is
+//! handed back to `available_actions_delete`. `step` is only called with an
+//! action from that array. Without `clone`, snapshots replay the actions
+//! played since the last reset on a new environment.
+//!
+//! The secret environments library exports the same table with a
+//! `secret_env_{id}_` prefix instead and no version symbol.
+
+use std::error::Error;
+use std::ffi::c_void;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use libloading::Library;
+use crate::{Environment, EnvError};
+
+/// Version of the function table described in the module documentation.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Prefix of every symbol a plugin exports.
+pub const PLUGIN_PREFIX: &str = "plugin_env_";
+
+/// Number of times a snapshot replays the history before giving up.
+pub const MAX_REPLAY_ATTEMPTS: usize = 1000;
+
+/// Why a plugin could not be loaded.
+#[derive(Debug)]
+pub enum PluginError {
+    /// The shared library is missing or could not be opened.
+    Library { path: PathBuf, reason: String },
+    /// The library does not export one of the required functions.
+    MissingSymbol { symbol: String },
+    /// The library was built against another version of the ABI.
+    Version { found: u32, expected: u32 },
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Library { path, reason } => {
+                write!(f, "Failed to load library {}: {}", path.display(), reason)
+            }
+            PluginError::MissingSymbol { symbol } => write!(f, "Missing symbol {} in library", symbol),
+            PluginError::Version { found, expected } => {
+                write!(f, "Plugin ABI version {} is not supported (expected {})", found, expected)
+            }
+        }
+    }
+}
+
+impl Error for PluginError {}
+
+/// Functions of one environment, resolved once when it is loaded and shared
+/// by all its clones.
+pub(crate) struct VTable {
+    new: unsafe extern "C" fn() -> *mut c_void,
+    delete: unsafe extern "C" fn(*mut c_void),
+    num_states: unsafe extern "C" fn() -> usize,
+    num_actions: unsafe extern "C" fn() -> usize,
+    state_id: unsafe extern "C" fn(*const c_void) -> usize,
+    reset: unsafe extern "C" fn(*mut c_void),
+    is_game_over: unsafe extern "C" fn(*const c_void) -> bool,
+    available_actions: unsafe extern "C" fn(*const c_void) -> *const usize,
+    available_actions_len: unsafe extern "C" fn(*const c_void) -> usize,
+    available_actions_delete: unsafe extern "C" fn(*const usize, usize),
+    score: unsafe extern "C" fn(*const c_void) -> f32,
+    step: unsafe extern "C" fn(*mut c_void, usize),
+    display: unsafe extern "C" fn(*const c_void),
+    clone: Option<unsafe extern "C" fn(*const c_void) -> *mut c_void>,
+    // Keeps the function pointers above valid
+    _lib: Library,
+}
+
+impl VTable {
+    /// Resolves the table exported with `prefix`, or returns the first missing symbol.
+    pub(crate) fn resolve(lib: Library, prefix: &str) -> Result<Self, String> {
+        unsafe fn symbol<T: Copy>(lib: &Library, prefix: &str, name: &str) -> Result<T, String> {
+            let symbol = format!("{}{}", prefix, name);
+            lib.get::<T>(symbol.as_bytes()).map(|f| *f).map_err(|_| symbol)
+        }
+
+        unsafe {
+            Ok(VTable {
+                new: symbol(&lib, prefix, "new")?,
+                delete: symbol(&lib, prefix, "delete")?,
+                num_states: symbol(&lib, prefix, "num_states")?,
+                num_actions: symbol(&lib, prefix, "num_actions")?,
+                state_id: symbol(&lib, prefix, "state_id")?,
+                reset: symbol(&lib, prefix, "reset")?,
+                is_game_over: symbol(&lib, prefix, "is_game_over")?,
+                available_actions: symbol(&lib, prefix, "available_actions")?,
+                available_actions_len: symbol(&lib, prefix, "available_actions_len")?,
+                available_actions_delete: symbol(&lib, prefix, "available_actions_delete")?,
+                score: symbol(&lib, prefix, "score")?,
+                step: symbol(&lib, prefix, "step")?,
+                display: symbol(&lib, prefix, "display")?,
+                clone: symbol(&lib, prefix, "clone").ok(),
+                _lib: lib,
+            })
+        }
+    }
+}
+
+/// An environment implemented in a shared library.
+///
+/// Unless the plugin exports `clone`, `PluginEnv` records the actions played
+/// since the last reset, with the state each one led to, and `snapshot`
+/// replays them on a new instance. A replay that reaches a different state,
+/// which stochastic environments can do, is started over up to
+/// `MAX_REPLAY_ATTEMPTS` times before the snapshot fails. There is no
+/// `Clone`, since copying can fail: use `snapshot` and handle the error.
+pub struct PluginEnv {
+    env_ptr: *mut c_void,
+    vtable: Arc<VTable>,
+    history: Vec<(usize, usize)>,
+}
+
+unsafe impl Send for PluginEnv {}
+unsafe impl Sync for PluginEnv {}
+
+impl PluginEnv {
+    /// Loads the plugin at `path`, checking its ABI version and resolving every
+    /// function the environment needs.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PluginError> {
+        let path = path.as_ref();
+        let lib = unsafe { Library::new(path) }.map_err(|e| PluginError::Library {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+
+        let version_symbol = format!("{}abi_version", PLUGIN_PREFIX);
+        let found = unsafe {
+            let version = lib
+                .get::<unsafe extern "C" fn() -> u32>(version_symbol.as_bytes())
+                .map_err(|_| PluginError::MissingSymbol { symbol: version_symbol.clone() })?;
+            version()
+        };
+        if found != PLUGIN_ABI_VERSION {
+            return Err(PluginError::Version { found, expected: PLUGIN_ABI_VERSION });
+        }
+
+        let vtable = VTable::resolve(lib, PLUGIN_PREFIX)
+            .map_err(|symbol| PluginError::MissingSymbol { symbol })?;
+        Ok(Self::from_vtable(Arc::new(vtable)))
+    }
+
+    pub(crate) fn from_vtable(vtable: Arc<VTable>) -> Self {
+        PluginEnv {
+            env_ptr: unsafe { (vtable.new)() },
+            vtable,
+            history: Vec::new(),
+        }
+    }
+
+    // Plays the recorded actions on a fresh instance until it goes through the same states
+    fn replay(&self) -> Option<Self> {
+        'attempts: for _ in 0..MAX_REPLAY_ATTEMPTS {
+            let mut env = self.fresh();
+            for &(action, state) in &self.history {
+                unsafe { (env.vtable.step)(env.env_ptr, action) }
+                if env.state_id() != state {
+                    continue 'attempts;
+                }
+            }
+            env.history = self.history.clone();
+            return Some(env);
+        }
+        None
+    }
+}
+
+impl Drop for PluginEnv {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.delete)(self.env_ptr) }
+    }
+}
+
+impl Environment for PluginEnv {
+    fn num_states(&self) -> usize {
+        unsafe { (self.vtable.num_states)() }
+    }
+
+    fn num_actions(&self) -> usize {
+        unsafe { (self.vtable.num_actions)() }
+    }
+
+    fn state_id(&self) -> usize {
+        unsafe { (self.vtable.state_id)(self.env_ptr) }
+    }
+
+    fn reset(&mut self) {
+        unsafe { (self.vtable.reset)(self.env_ptr) }
+        self.history.clear();
+    }
+
+    fn is_game_over(&self) -> bool {
+        unsafe { (self.vtable.is_game_over)(self.env_ptr) }
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        unsafe {
+            let actions_ptr = (self.vtable.available_actions)(self.env_ptr);
+            let len = (self.vtable.available_actions_len)(self.env_ptr);
+            let actions = std::slice::from_raw_parts(actions_ptr, len).to_vec();
+            (self.vtable.available_actions_delete)(actions_ptr, len);
+            actions
+        }
+    }
+
+    fn score(&self) -> f32 {
+        unsafe { (self.vtable.score)(self.env_ptr) }
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+        unsafe { (self.vtable.step)(self.env_ptr, action) }
+        if self.vtable.clone.is_none() {
+            self.history.push((action, self.state_id()));
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        unsafe { (self.vtable.display)(self.env_ptr) }
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = Self::from_vtable(Arc::clone(&self.vtable));
+        env.reset();
+        env
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        if let Some(clone) = self.vtable.clone {
+            return Ok(PluginEnv {
+                env_ptr: unsafe { clone(self.env_ptr) },
+                vtable: Arc::clone(&self.vtable),
+                history: Vec::new(),
+            });
+        }
+        self.replay().ok_or_else(|| EnvError::Backend(format!(
+            "Could not reproduce the environment state by replaying {} actions",
+            self.history.len()
+        )))
+    }
+}
+
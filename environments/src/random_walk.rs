@@ -0,0 +1,145 @@
+use crate::{Environment, EnvError};
+
+/// The random walk used for prediction experiments in Sutton & Barto
+/// (Example 6.2 and the 19-state version of Chapter 7).
+///
+/// Non-terminal positions are the state ids `0..size` and the walk starts in
+/// the middle. Moving left (0) from position 0 ends the episode with -1,
+/// moving right (1) from the last position ends it with +1. The two terminal
+/// states use ids `size` (left) and `size + 1` (right).
+#[derive(Clone)]
+pub struct RandomWalk {
+    pos: usize,
+    size: usize,
+    terminal: Option<usize>,
+}
+
+impl RandomWalk {
+    pub fn new() -> Self {
+        Self::with_size(19)
+    }
+
+    pub fn with_size(size: usize) -> Self {
+        assert!(size > 0, "The walk needs at least one non-terminal state");
+        RandomWalk {
+            pos: size / 2,
+            size,
+            terminal: None,
+        }
+    }
+
+    /// Exact state values of the non-terminal states under the equiprobable
+    /// random policy, indexed by state id.
+    pub fn true_values(&self) -> Vec<f32> {
+        (1..=self.size)
+            .map(|k| -1.0 + 2.0 * k as f32 / (self.size + 1) as f32)
+            .collect()
+    }
+}
+
+impl Default for RandomWalk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for RandomWalk {
+    fn num_states(&self) -> usize {
+        self.size + 2
+    }
+
+    fn num_actions(&self) -> usize {
+        2
+    }
+
+    fn state_id(&self) -> usize {
+        self.terminal.unwrap_or(self.pos)
+    }
+
+    fn reset(&mut self) {
+        self.pos = self.size / 2;
+        self.terminal = None;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.terminal.is_some()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            vec![0, 1]
+        }
+    }
+
+    fn score(&self) -> f32 {
+        match self.terminal {
+            Some(t) if t == self.size => -1.0,
+            Some(_) => 1.0,
+            None => 0.0,
+        }
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        match action {
+            0 if self.pos == 0 => self.terminal = Some(self.size),
+            0 => self.pos -= 1,
+            1 if self.pos == self.size - 1 => self.terminal = Some(self.size + 1),
+            1 => self.pos += 1,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        print!("[");
+        for s in 0..self.size {
+            if self.terminal.is_none() && self.pos == s {
+                print!("X");
+            } else {
+                print!("_");
+            }
+        }
+        println!("]");
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_true_values_of_example_6_2() {
+        let values = RandomWalk::with_size(5).true_values();
+        let expected = [-2.0 / 3.0, -1.0 / 3.0, 0.0, 1.0 / 3.0, 2.0 / 3.0];
+        for (v, e) in values.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-6, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn test_true_values_solve_the_bellman_equation() {
+        let env = RandomWalk::new();
+        let values = env.true_values();
+        assert_eq!(values.len(), env.num_states() - 2);
+        // Both neighbours are equally likely; the ends lead to -1 and +1
+        for s in 0..values.len() {
+            let left = if s == 0 { -1.0 } else { values[s - 1] };
+            let right = values.get(s + 1).copied().unwrap_or(1.0);
+            assert!((values[s] - 0.5 * (left + right)).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,545 @@
+//! Environments served by another process over a local socket.
+//!
+//! The protocol is line-delimited JSON: the client writes one request per
+//! line and the server answers each with one line. A connection can hold
+//! several environments, addressed by the id `new` or `snapshot` returned.
+//!
+//! ```text
+//! -> {"method":"new"}
+//! <- {"ok":{"env":0,"num_actions":2,"num_states":5,"status":{"available_actions":[0,1],"is_game_over":false,"score":0.0,"state_id":2}}}
+//! -> {"env":0,"method":"step","action":1}
+//! <- {"ok":{"available_actions":[0,1],"is_game_over":false,"score":0.0,"state_id":3}}
+//! -> {"env":0,"method":"step","action":7}
+//! <- {"error":{"IllegalAction":{"action":7,"legal":[0,1]}}}
+//! ```
+//!
+//! `new` and `snapshot` describe the environment they create, and `step` and
+//! `reset` answer with the status of the environment afterwards, so a client
+//! only needs a round trip for the calls that change the state. The other
+//! methods without arguments are `num_states`, `num_actions`, `state_id`,
+//! `is_game_over`, `available_actions`, `score`, `display` (printed by the
+//! server) and `close`. Errors use the serialized form of `EnvError`;
+//! malformed requests get a `Backend` error.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::{Environment, EnvError};
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum Call {
+    New,
+    Snapshot,
+    Close,
+    NumStates,
+    NumActions,
+    StateId,
+    Reset,
+    IsGameOver,
+    AvailableActions,
+    Score,
+    Step { action: usize },
+    Display,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    #[serde(default)]
+    env: u64,
+    #[serde(flatten)]
+    call: Call,
+}
+
+// What an environment looks like after a call that changes it
+#[derive(Clone, Serialize, Deserialize)]
+struct Status {
+    state_id: usize,
+    is_game_over: bool,
+    score: f32,
+    available_actions: Vec<usize>,
+}
+
+impl Status {
+    fn of(env: &impl Environment) -> Self {
+        Status {
+            state_id: env.state_id(),
+            is_game_over: env.is_game_over(),
+            score: env.score(),
+            available_actions: env.available_actions(),
+        }
+    }
+}
+
+// Answer to `new` and `snapshot`
+#[derive(Serialize, Deserialize)]
+struct Created {
+    env: u64,
+    num_states: usize,
+    num_actions: usize,
+    status: Status,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Ok(Value),
+    Error(EnvError),
+}
+
+/// Serves environments created from a prototype, one session per connection.
+pub struct EnvServer<E> {
+    prototype: E,
+}
+
+// The environments of one connection
+struct Session<E> {
+    prototype: E,
+    envs: HashMap<u64, E>,
+    next_id: u64,
+}
+
+impl<E: Environment> Session<E> {
+    fn add(&mut self, env: E) -> Created {
+        let id = self.next_id;
+        self.next_id += 1;
+        let created = Created {
+            env: id,
+            num_states: env.num_states(),
+            num_actions: env.num_actions(),
+            status: Status::of(&env),
+        };
+        self.envs.insert(id, env);
+        created
+    }
+
+    fn handle(&mut self, request: Request) -> Result<Value, EnvError> {
+        match request.call {
+            Call::New => {
+                let env = self.prototype.fresh();
+                return to_value(self.add(env));
+            }
+            Call::Close => {
+                self.envs.remove(&request.env);
+                return Ok(Value::Null);
+            }
+            _ => {}
+        }
+
+        let env = self.envs.get_mut(&request.env)
+            .ok_or_else(|| EnvError::Backend(format!("Unknown remote environment {}", request.env)))?;
+        Ok(match request.call {
+            Call::New | Call::Close => unreachable!(),
+            Call::Snapshot => {
+                let snapshot = env.snapshot()?;
+                return to_value(self.add(snapshot));
+            }
+            Call::NumStates => env.num_states().into(),
+            Call::NumActions => env.num_actions().into(),
+            Call::StateId => env.state_id().into(),
+            Call::Reset => {
+                env.reset();
+                return to_value(Status::of(env));
+            }
+            Call::IsGameOver => env.is_game_over().into(),
+            Call::AvailableActions => env.available_actions().into(),
+            Call::Score => env.score().into(),
+            Call::Step { action } => {
+                env.step(action)?;
+                return to_value(Status::of(env));
+            }
+            Call::Display => {
+                env.display();
+                Value::Null
+            }
+        })
+    }
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, EnvError> {
+    serde_json::to_value(value).map_err(|e| EnvError::Backend(e.to_string()))
+}
+
+// Reports how the finished connections ended and keeps the others
+fn reap(connections: &mut Vec<JoinHandle<io::Result<()>>>) {
+    let (finished, running): (Vec<_>, Vec<_>) = connections.drain(..).partition(|c| c.is_finished());
+    *connections = running;
+    for connection in finished {
+        match connection.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("Remote environment connection failed: {}", e),
+            Err(_) => println!("Remote environment connection panicked"),
+        }
+    }
+}
+
+impl<E: Environment + Send + 'static> EnvServer<E> {
+    pub fn new(prototype: E) -> Self {
+        EnvServer { prototype }
+    }
+
+    /// Answers requests from `reader` on `writer` until the client disconnects.
+    pub fn serve_connection(&self, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+        let mut session = Session { prototype: self.prototype.fresh(), envs: HashMap::new(), next_id: 0 };
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let result = serde_json::from_str::<Request>(&line)
+                .map_err(|e| EnvError::Backend(format!("Invalid request: {}", e)))
+                .and_then(|request| session.handle(request));
+            let response = match result {
+                Ok(value) => Response::Ok(value),
+                Err(e) => Response::Error(e),
+            };
+            serde_json::to_writer(&mut writer, &response)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Accepts connections until the listener fails, each served on its own
+    /// thread. Connections that end with an error or a panic are reported
+    /// when the next client connects.
+    pub fn serve_tcp(self, listener: TcpListener) -> io::Result<()> {
+        let mut connections = Vec::new();
+        for stream in listener.incoming() {
+            reap(&mut connections);
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            let server = EnvServer::new(self.prototype.fresh());
+            connections.push(thread::spawn(move || {
+                let reader = BufReader::new(stream.try_clone()?);
+                server.serve_connection(reader, stream)
+            }));
+        }
+        Ok(())
+    }
+
+    /// Like `serve_tcp`, on a Unix socket.
+    #[cfg(unix)]
+    pub fn serve_unix(self, listener: UnixListener) -> io::Result<()> {
+        let mut connections = Vec::new();
+        for stream in listener.incoming() {
+            reap(&mut connections);
+            let stream = stream?;
+            let server = EnvServer::new(self.prototype.fresh());
+            connections.push(thread::spawn(move || {
+                let reader = BufReader::new(stream.try_clone()?);
+                server.serve_connection(reader, stream)
+            }));
+        }
+        Ok(())
+    }
+
+    /// Binds `addr` and serves on a background thread. Returns the bound
+    /// address, which tells the port when `addr` asks for port 0.
+    pub fn spawn_tcp(self, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        thread::spawn(move || {
+            if let Err(e) = self.serve_tcp(listener) {
+                println!("Remote environment server stopped: {}", e);
+            }
+        });
+        Ok(addr)
+    }
+}
+
+struct Connection {
+    reader: Box<dyn BufRead + Send>,
+    writer: Box<dyn Write + Send>,
+}
+
+impl Connection {
+    fn call(&mut self, env: u64, call: Call) -> Result<Value, EnvError> {
+        let lost = |e: io::Error| EnvError::Backend(format!("Lost the environment server: {}", e));
+
+        let mut request = serde_json::to_vec(&Request { env, call })
+            .map_err(|e| EnvError::Backend(e.to_string()))?;
+        request.push(b'\n');
+        self.writer.write_all(&request).map_err(lost)?;
+        self.writer.flush().map_err(lost)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(lost)? == 0 {
+            return Err(lost(io::ErrorKind::UnexpectedEof.into()));
+        }
+        match serde_json::from_str(&line) {
+            Ok(Response::Ok(value)) => Ok(value),
+            Ok(Response::Error(e)) => Err(e),
+            Err(e) => Err(EnvError::Backend(format!("Invalid response: {}", e))),
+        }
+    }
+}
+
+/// An environment living in an `EnvServer`, possibly in another process.
+///
+/// Only `step`, `reset`, `snapshot`, `fresh` and `display` talk to the
+/// server: the state id, score, legal actions and game-over flag are cached
+/// from the last answer. `step` and `snapshot` surface connection failures
+/// as `EnvError::Backend`. When `reset` or `fresh` cannot reach the server,
+/// which they cannot report, the environment is left failed: it claims every
+/// action is available and is not over, so that the next `step` returns the
+/// error. Copies are snapshots taken by the server, sharing this connection;
+/// there is no `Clone` since they can fail.
+pub struct RemoteEnv {
+    connection: Arc<Mutex<Connection>>,
+    env: u64,
+    num_states: usize,
+    num_actions: usize,
+    status: Status,
+    failure: Option<EnvError>,
+}
+
+impl RemoteEnv {
+    pub fn connect_tcp(addr: impl ToSocketAddrs) -> Result<Self, EnvError> {
+        let stream = TcpStream::connect(addr).map_err(|e| EnvError::Backend(e.to_string()))?;
+        // Every request waits for its answer, so batching small writes only adds latency
+        stream.set_nodelay(true).map_err(|e| EnvError::Backend(e.to_string()))?;
+        let reader = stream.try_clone().map_err(|e| EnvError::Backend(e.to_string()))?;
+        Self::open(reader, stream)
+    }
+
+    #[cfg(unix)]
+    pub fn connect_unix(path: impl AsRef<Path>) -> Result<Self, EnvError> {
+        let stream = UnixStream::connect(path).map_err(|e| EnvError::Backend(e.to_string()))?;
+        let reader = stream.try_clone().map_err(|e| EnvError::Backend(e.to_string()))?;
+        Self::open(reader, stream)
+    }
+
+    fn open(reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Result<Self, EnvError> {
+        let connection = Connection { reader: Box::new(BufReader::new(reader)), writer: Box::new(writer) };
+        Self::attach(Arc::new(Mutex::new(connection)), 0, Call::New)
+    }
+
+    // Creates an environment on the server, with `New` or a `Snapshot` of `target`
+    fn attach(connection: Arc<Mutex<Connection>>, target: u64, call: Call) -> Result<Self, EnvError> {
+        let value = connection.lock().unwrap().call(target, call)?;
+        let created: Created = parse(value)?;
+        Ok(RemoteEnv {
+            connection,
+            env: created.env,
+            num_states: created.num_states,
+            num_actions: created.num_actions,
+            status: created.status,
+            failure: None,
+        })
+    }
+
+    fn call<T: DeserializeOwned>(&self, call: Call) -> Result<T, EnvError> {
+        parse(self.connection.lock().unwrap().call(self.env, call)?)
+    }
+
+    fn fail(&mut self, error: EnvError) {
+        self.status.is_game_over = false;
+        self.status.available_actions = (0..self.num_actions).collect();
+        self.failure = Some(error);
+    }
+
+    /// The error that left this environment failed, if any.
+    pub fn failure(&self) -> Option<&EnvError> {
+        self.failure.as_ref()
+    }
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, EnvError> {
+    serde_json::from_value(value).map_err(|e| EnvError::Backend(format!("Invalid response: {}", e)))
+}
+
+impl Drop for RemoteEnv {
+    fn drop(&mut self) {
+        if self.failure.is_none() {
+            let _ = self.call::<Value>(Call::Close);
+        }
+    }
+}
+
+impl Environment for RemoteEnv {
+    fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    fn num_actions(&self) -> usize {
+        self.num_actions
+    }
+
+    fn state_id(&self) -> usize {
+        self.status.state_id
+    }
+
+    fn reset(&mut self) {
+        if self.failure.is_some() {
+            return;
+        }
+        match self.call(Call::Reset) {
+            Ok(status) => self.status = status,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.status.is_game_over
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.status.available_actions.clone()
+    }
+
+    fn score(&self) -> f32 {
+        self.status.score
+    }
+
+    // The server checks the action, so an illegal one costs a single round trip
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        if let Some(failure) = &self.failure {
+            return Err(failure.clone());
+        }
+        self.status = self.call(Call::Step { action })?;
+        Ok(())
+    }
+
+    fn display(&self) {
+        if let Err(e) = self.call::<Value>(Call::Display) {
+            println!("{}", e);
+        }
+    }
+
+    fn fresh(&self) -> Self {
+        Self::attach(Arc::clone(&self.connection), 0, Call::New).unwrap_or_else(|e| {
+            let mut env = RemoteEnv {
+                connection: Arc::clone(&self.connection),
+                env: self.env,
+                num_states: self.num_states,
+                num_actions: self.num_actions,
+                status: self.status.clone(),
+                failure: None,
+            };
+            env.fail(e);
+            env
+        })
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        if let Some(failure) = &self.failure {
+            return Err(failure.clone());
+        }
+        Self::attach(Arc::clone(&self.connection), self.env, Call::Snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_world::LineWorld;
+
+    fn connect() -> RemoteEnv {
+        let addr = EnvServer::new(LineWorld::new()).spawn_tcp("127.0.0.1:0").unwrap();
+        RemoteEnv::connect_tcp(addr).unwrap()
+    }
+
+    #[test]
+    fn test_remote_env_plays_like_the_served_one() {
+        let mut remote = connect();
+        let mut local = LineWorld::new();
+        assert_eq!(remote.num_states(), local.num_states());
+        assert_eq!(remote.num_actions(), local.num_actions());
+
+        for action in [1, 0, 0, 0] {
+            remote.step(action).unwrap();
+            local.step(action).unwrap();
+            assert_eq!(remote.state_id(), local.state_id());
+            assert_eq!(remote.available_actions(), local.available_actions());
+            assert_eq!(remote.score(), local.score());
+            if local.is_game_over() {
+                break;
+            }
+        }
+        assert!(remote.is_game_over());
+        assert_eq!(remote.step(1), Err(EnvError::GameOver));
+
+        remote.reset();
+        assert_eq!(remote.state_id(), 2);
+        assert_eq!(remote.step(5), Err(EnvError::IllegalAction { action: 5, legal: vec![0, 1] }));
+    }
+
+    #[test]
+    fn test_remote_snapshot_is_independent() {
+        let mut env = connect();
+        env.step(1).unwrap();
+
+        let mut snapshot = env.snapshot().unwrap();
+        snapshot.step(1).unwrap();
+        assert!(snapshot.is_game_over());
+        assert_eq!(env.state_id(), 3);
+        assert_eq!(env.fresh().state_id(), 2);
+    }
+
+    #[test]
+    fn test_lost_server_fails_steps_without_panicking() {
+        // A server that creates the environment, then hangs up
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(stream.try_clone().unwrap()).read_line(&mut request).unwrap();
+            EnvServer::new(LineWorld::new()).serve_connection(request.as_bytes(), &stream).unwrap();
+        });
+
+        let mut env = RemoteEnv::connect_tcp(addr).unwrap();
+        assert_eq!(env.state_id(), 2);
+        assert_eq!(env.available_actions(), vec![0, 1]);
+        assert!(matches!(env.step(1), Err(EnvError::Backend(_))));
+        assert!(env.snapshot().is_err());
+
+        env.reset();
+        assert!(env.failure().is_some());
+        assert!(!env.is_game_over());
+        assert!(matches!(env.step(1), Err(EnvError::Backend(_))));
+        let mut fresh = env.fresh();
+        assert!(matches!(fresh.step(0), Err(EnvError::Backend(_))));
+    }
+
+    #[test]
+    fn test_malformed_requests_get_an_error_line() {
+        let server = EnvServer::new(LineWorld::new());
+        let requests = "{\"method\":\"new\"}\nnot json\n{\"env\":3,\"method\":\"score\"}\n";
+        let mut output = Vec::new();
+        server.serve_connection(requests.as_bytes(), &mut output).unwrap();
+
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        let created: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(created["ok"]["env"], 0);
+        assert_eq!(created["ok"]["status"]["state_id"], 2);
+        assert!(lines[1].starts_with("{\"error\":{\"Backend\":\"Invalid request"));
+        assert_eq!(lines[2], "{\"error\":{\"Backend\":\"Unknown remote environment 3\"}}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remote_env_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("remote_env_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || EnvServer::new(LineWorld::new()).serve_unix(listener));
+
+        let mut env = RemoteEnv::connect_unix(&path).unwrap();
+        env.step(1).unwrap();
+        env.step(1).unwrap();
+        assert_eq!(env.score(), 1.0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
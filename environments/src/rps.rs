@@ -1,6 +1,199 @@
 use std::io::{self, Write};
-use rand::Rng;
-use crate::Environment;
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError, PolicyFn};
+
+const NUM_MOVES: usize = 3;
+
+fn beats(move_num: usize) -> usize {
+    (move_num + 1) % NUM_MOVES  // Paper beats Rock, Scissors beat Paper, Rock beats Scissors
+}
+
+/// How past rounds are packed into `state_id`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoryEncoding {
+    /// Only the agent's moves: one base-4 digit per round (3 = no move yet).
+    AgentOnly,
+    /// Both players' moves: one base-10 digit per round, `agent * 3 + opponent`
+    /// (9 = no move yet).
+    Both,
+}
+
+impl HistoryEncoding {
+    fn base(self) -> usize {
+        match self {
+            HistoryEncoding::AgentOnly => NUM_MOVES + 1,
+            HistoryEncoding::Both => NUM_MOVES * NUM_MOVES + 1,
+        }
+    }
+}
+
+/// Encodes the last `history_len` rounds, most recent first. `history` holds
+/// (agent move, opponent move) pairs; with `mirrored` the roles are swapped so
+/// that the opponent sees the game from its own point of view.
+pub fn encode_history(history: &[(usize, usize)], history_len: usize, encoding: HistoryEncoding, mirrored: bool) -> usize {
+    let base = encoding.base();
+    let mut state = 0;
+    let mut weight = 1;
+
+    for i in 0..history_len {
+        let digit = match history.len().checked_sub(i + 1).map(|t| history[t]) {
+            Some((agent, opponent)) => {
+                let (own, other) = if mirrored { (opponent, agent) } else { (agent, opponent) };
+                match encoding {
+                    HistoryEncoding::AgentOnly => own,
+                    HistoryEncoding::Both => own * NUM_MOVES + other,
+                }
+            }
+            None => base - 1,
+        };
+        state += digit * weight;
+        weight *= base;
+    }
+
+    state
+}
+
+/// A scripted opponent. `history` holds the (agent move, opponent move) pairs
+/// of the previous rounds of the current game.
+pub trait OpponentStrategy: Send + Sync {
+    fn next_move(&mut self, history: &[(usize, usize)], rng: &mut Xoshiro256PlusPlus) -> usize;
+    /// Called at the start of every game.
+    fn reset(&mut self) {}
+    /// Called with the whole history when a game ends, since `next_move`
+    /// never sees its last round.
+    fn game_over(&mut self, _history: &[(usize, usize)]) {}
+    fn box_clone(&self) -> Box<dyn OpponentStrategy>;
+}
+
+impl Clone for Box<dyn OpponentStrategy> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Plays randomly in the first round, then copies the agent's previous move.
+#[derive(Clone)]
+pub struct RandomThenCopy;
+
+impl OpponentStrategy for RandomThenCopy {
+    fn next_move(&mut self, history: &[(usize, usize)], rng: &mut Xoshiro256PlusPlus) -> usize {
+        match history.last() {
+            None => rng.random_range(0..NUM_MOVES),
+            Some(&(agent, _)) => agent,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn OpponentStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+/// Draws every move from fixed probabilities for Rock, Paper and Scissors.
+#[derive(Clone)]
+pub struct Biased {
+    pub weights: [f32; NUM_MOVES],
+}
+
+impl OpponentStrategy for Biased {
+    fn next_move(&mut self, _history: &[(usize, usize)], rng: &mut Xoshiro256PlusPlus) -> usize {
+        let total: f32 = self.weights.iter().sum();
+        let r = rng.random::<f32>() * total;
+        let mut cumulative = 0.0;
+        for (move_num, weight) in self.weights.iter().enumerate() {
+            cumulative += weight;
+            if r < cumulative {
+                return move_num;
+            }
+        }
+        NUM_MOVES - 1
+    }
+
+    fn box_clone(&self) -> Box<dyn OpponentStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+/// Repeats a fixed sequence of moves.
+#[derive(Clone)]
+pub struct Cyclic {
+    pub sequence: Vec<usize>,
+}
+
+impl OpponentStrategy for Cyclic {
+    fn next_move(&mut self, history: &[(usize, usize)], _rng: &mut Xoshiro256PlusPlus) -> usize {
+        self.sequence[history.len() % self.sequence.len()]
+    }
+
+    fn box_clone(&self) -> Box<dyn OpponentStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+/// Keeps a move after winning, otherwise switches to the move that would have
+/// beaten the agent's last move.
+#[derive(Clone)]
+pub struct WinStayLoseShift;
+
+impl OpponentStrategy for WinStayLoseShift {
+    fn next_move(&mut self, history: &[(usize, usize)], rng: &mut Xoshiro256PlusPlus) -> usize {
+        match history.last() {
+            None => rng.random_range(0..NUM_MOVES),
+            Some(&(agent, opponent)) if opponent == beats(agent) => opponent,
+            Some(&(agent, _)) => beats(agent),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn OpponentStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+/// Counters the agent's most frequent move, counted across games.
+#[derive(Clone, Default)]
+pub struct FrequencyCounter {
+    counts: [usize; NUM_MOVES],
+}
+
+impl OpponentStrategy for FrequencyCounter {
+    fn next_move(&mut self, history: &[(usize, usize)], rng: &mut Xoshiro256PlusPlus) -> usize {
+        if let Some(&(agent, _)) = history.last() {
+            self.counts[agent] += 1;
+        }
+        let max = *self.counts.iter().max().unwrap();
+        let favourites: Vec<usize> = (0..NUM_MOVES).filter(|&m| self.counts[m] == max).collect();
+        beats(*favourites.choose(rng).unwrap())
+    }
+
+    fn game_over(&mut self, history: &[(usize, usize)]) {
+        if let Some(&(agent, _)) = history.last() {
+            self.counts[agent] += 1;
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn OpponentStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+/// Another trained agent, which sees the history with the roles swapped.
+#[derive(Clone)]
+pub struct PolicyOpponent {
+    pub policy: PolicyFn,
+    pub history_len: usize,
+    pub encoding: HistoryEncoding,
+}
+
+impl OpponentStrategy for PolicyOpponent {
+    fn next_move(&mut self, history: &[(usize, usize)], _rng: &mut Xoshiro256PlusPlus) -> usize {
+        let state = encode_history(history, self.history_len, self.encoding, true);
+        (self.policy)(state, &[0, 1, 2]).min(NUM_MOVES - 1)
+    }
+
+    fn box_clone(&self) -> Box<dyn OpponentStrategy> {
+        Box::new(self.clone())
+    }
+}
 
 #[derive(Clone)]
 pub struct RPS {
@@ -9,9 +202,18 @@ pub struct RPS {
     pub player_score: f32,
     pub agent_last_move: Option<usize>,
     pub human_mode: bool,  // New field to track mode
+    history: Vec<(usize, usize)>,
+    history_len: usize,
+    encoding: HistoryEncoding,
+    opponent: Box<dyn OpponentStrategy>,
+    rng: Xoshiro256PlusPlus,
 }
 
 impl RPS {
+    pub fn new() -> Self {
+        Self::new_with_mode(false)  // Default to training mode
+    }
+
     // New constructor with mode
     pub fn new_with_mode(human_mode: bool) -> Self {
         RPS {
@@ -20,18 +222,35 @@ impl RPS {
             player_score: 0.0,
             agent_last_move: None,
             human_mode,
+            history: Vec::new(),
+            history_len: 1,
+            encoding: HistoryEncoding::AgentOnly,
+            opponent: Box::new(RandomThenCopy),
+            rng: Xoshiro256PlusPlus::seed_from_u64(42),
+        }
+    }
+
+    /// Iterated game of `max_rounds` rounds whose state is the last
+    /// `history_len` moves of both players.
+    pub fn iterated(max_rounds: usize, history_len: usize, opponent: Box<dyn OpponentStrategy>) -> Self {
+        RPS {
+            max_rounds,
+            history_len,
+            encoding: HistoryEncoding::Both,
+            opponent,
+            ..Self::new_with_mode(false)
         }
     }
 
-    fn get_opponent_move(&self) -> usize {
+    pub fn history(&self) -> &[(usize, usize)] {
+        &self.history
+    }
+
+    fn get_opponent_move(&mut self) -> usize {
         if self.human_mode {
             Self::get_human_move()
         } else {
-            match self.current_round {
-                0 => rand::thread_rng().gen_range(0..3),  // First round: random
-                1 => self.agent_last_move.expect("Agent's last move should be recorded"), // Second round: copy agent's move
-                _ => unreachable!("Game should only have 2 rounds")
-            }
+            self.opponent.next_move(&self.history, &mut self.rng)
         }
     }
 
@@ -68,13 +287,15 @@ impl RPS {
     }
 }
 
-impl Environment for RPS {
-    fn new() -> Self {
-        Self::new_with_mode(false)  // Default to training mode
+impl Default for RPS {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
+impl Environment for RPS {
     fn num_states(&self) -> usize {
-        4  // 3 states for first round + 1 initial state
+        self.encoding.base().pow(self.history_len as u32)
     }
 
     fn num_actions(&self) -> usize {
@@ -82,16 +303,15 @@ impl Environment for RPS {
     }
 
     fn state_id(&self) -> usize {
-        match self.agent_last_move {
-            None => 3,  // Initial state
-            Some(last_move) => last_move,
-        }
+        encode_history(&self.history, self.history_len, self.encoding, false)
     }
 
     fn reset(&mut self) {
         self.current_round = 0;
         self.player_score = 0.0;
         self.agent_last_move = None;
+        self.history.clear();
+        self.opponent.reset();
     }
 
     fn is_game_over(&self) -> bool {
@@ -110,13 +330,8 @@ impl Environment for RPS {
         self.player_score
     }
 
-    fn step(&mut self, agent_action: usize) {
-        if self.is_game_over() {
-            panic!("We are trying to play but game is over!");
-        }
-        if !self.available_actions().contains(&agent_action) {
-            panic!("Unauthorized action!");
-        }
+    fn step(&mut self, agent_action: usize) -> Result<(), EnvError> {
+        self.check_action(agent_action)?;
 
         let opponent_move = self.get_opponent_move();
         let round_score = self.calculate_round_outcome(agent_action, opponent_move);
@@ -133,12 +348,14 @@ impl Environment for RPS {
             }
         }
 
-        if self.current_round == 0 {
-            self.agent_last_move = Some(agent_action);
-        }
-
+        self.agent_last_move = Some(agent_action);
+        self.history.push((agent_action, opponent_move));
         self.player_score += round_score;
         self.current_round += 1;
+        if self.is_game_over() && !self.human_mode {
+            self.opponent.game_over(&self.history);
+        }
+        Ok(())
     }
 
     fn display(&self) {
@@ -150,4 +367,87 @@ impl Environment for RPS {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_encoding_matches_two_round_game() {
+        let mut env = RPS::new();
+        assert_eq!(env.num_states(), 4);
+        assert_eq!(env.state_id(), 3);
+        env.step(1).unwrap();
+        assert_eq!(env.state_id(), 1);
+        env.step(2).unwrap();
+        assert!(env.is_game_over());
+    }
+
+    #[test]
+    fn test_history_encoding_of_both_players() {
+        let history = [(0, 1), (2, 0)];
+        assert_eq!(encode_history(&history, 2, HistoryEncoding::Both, false), 6 + 10);
+        assert_eq!(encode_history(&history, 2, HistoryEncoding::Both, true), 2 + 3 * 10);
+        assert_eq!(encode_history(&history, 3, HistoryEncoding::Both, false), 6 + 10 + 900);
+    }
+
+    #[test]
+    fn test_scripted_opponents() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+
+        let mut cyclic = Cyclic { sequence: vec![0, 1] };
+        assert_eq!(cyclic.next_move(&[(0, 0)], &mut rng), 1);
+
+        let mut wsls = WinStayLoseShift;
+        assert_eq!(wsls.next_move(&[(0, 1)], &mut rng), 1);  // Paper beat Rock: stay
+        assert_eq!(wsls.next_move(&[(0, 2)], &mut rng), 1);  // Lost with Scissors: play what beats Rock
+
+        let mut counter = FrequencyCounter::default();
+        for _ in 0..3 {
+            counter.next_move(&[(2, 0)], &mut rng);
+        }
+        assert_eq!(counter.next_move(&[(2, 0)], &mut rng), 0);  // Rock beats Scissors
+    }
+
+    #[test]
+    fn test_frequency_counter_sees_the_last_round_of_each_game() {
+        // One-round games: every move the counter sees is a last one
+        let mut env = RPS::iterated(1, 1, Box::new(FrequencyCounter::default()));
+        env.step(2).unwrap();
+        for _ in 0..5 {
+            env.reset();
+            env.step(2).unwrap();
+            assert_eq!(env.history(), &[(2, 0)]);  // Rock beats Scissors
+        }
+    }
+
+    #[test]
+    fn test_iterated_game_length() {
+        let mut env = RPS::iterated(10, 2, Box::new(Cyclic { sequence: vec![0, 1, 2] }));
+        assert_eq!(env.num_states(), 100);
+        let mut rounds = 0;
+        while !env.is_game_over() {
+            env.step(1).unwrap();
+            rounds += 1;
+            assert!(env.state_id() < env.num_states());
+        }
+        assert_eq!(rounds, 10);
+    }
+}
@@ -1,178 +1,237 @@
+use std::env;
+use std::error::Error;
 use std::ffi::c_void;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use libloading::{Library, Symbol};
-use crate::Environment;
+use libloading::Library;
+use crate::{Environment, EnvError};
+use crate::plugin::{PluginEnv, VTable};
+
+/// Environment variable that overrides the path of the secret environments library.
+pub const LIBRARY_PATH_VAR: &str = "SECRET_ENVS_LIB";
+
+/// Why a secret environment could not be loaded.
+#[derive(Debug)]
+pub enum SecretEnvError {
+    /// The shared library is missing or could not be opened.
+    Library { path: PathBuf, reason: String },
+    /// The library exports the environment but not one of its functions.
+    MissingSymbol { symbol: String },
+    /// The library does not export this environment at all.
+    UnknownEnv { env_id: usize, available: usize },
+}
 
+impl fmt::Display for SecretEnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretEnvError::Library { path, reason } => {
+                write!(f, "Failed to load library {}: {}", path.display(), reason)
+            }
+            SecretEnvError::MissingSymbol { symbol } => write!(f, "Missing symbol {} in library", symbol),
+            SecretEnvError::UnknownEnv { env_id, available } => {
+                write!(f, "Unknown secret environment {} (the library exports {})", env_id, available)
+            }
+        }
+    }
+}
+
+impl Error for SecretEnvError {}
+
+/// An environment from the secret environments library, which exports the
+/// plugin function table once per environment with a `secret_env_{id}_` prefix.
+///
+/// The library offers no way to copy an environment, so snapshots replay the
+/// actions played since the last reset (see `PluginEnv`). Secret Environment 3
+/// is stochastic, so its snapshots can fail after a few unlikely transitions.
 pub struct SecretEnv {
-    env_ptr: *mut c_void,
-    lib: Arc<Library>,
+    env: PluginEnv,
     env_id: usize,
 }
 
-unsafe impl Send for SecretEnv {}
-unsafe impl Sync for SecretEnv {}
+impl SecretEnv {
+    /// Loads environment `env_id` from the default library, panicking on failure.
+    /// Use `from_default_library` or `load` to handle errors.
+    pub fn new(env_id: usize) -> Self {
+        Self::from_default_library(env_id).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-impl Clone for SecretEnv {
-    fn clone(&self) -> Self {
-        unsafe {
-            // Create a new environment with the same library
-            let new_fn: Symbol<unsafe extern fn() -> *mut c_void> =
-                self.lib.get(format!("secret_env_{}_new", self.env_id).as_bytes())
-                    .expect("Failed to load new function");
+    /// Path of the bundled library for this platform, unless `SECRET_ENVS_LIB` is set.
+    pub fn default_library_path() -> PathBuf {
+        if let Some(path) = env::var_os(LIBRARY_PATH_VAR) {
+            return PathBuf::from(path);
+        }
 
-            let env_ptr = new_fn();
+        #[cfg(target_os = "linux")]
+        let path = "./libs/libsecret_envs.so";
+        #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
+        let path = "./libs/libsecret_envs_intel_macos.dylib";
+        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+        let path = "./libs/libsecret_envs.dylib";
+        #[cfg(windows)]
+        let path = "./libs/secret_envs.dll";
 
-            SecretEnv {
-                env_ptr,
-                lib: Arc::clone(&self.lib),
-                env_id: self.env_id,
-            }
-        }
+        PathBuf::from(path)
     }
-}
 
-impl SecretEnv {
-    pub fn new(env_id: usize) -> Self {
-        unsafe {
-            #[cfg(target_os = "linux")]
-            let path = "./libs/libsecret_envs.so";
-            #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-            let path = "./libs/libsecret_envs_intel_macos.dylib";
-            #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-            let path = "./libs/libsecret_envs.dylib";
-            #[cfg(windows)]
-            let path = "./libs/secret_envs.dll";
-
-            let lib = Arc::new(Library::new(path).expect("Failed to load library"));
-
-            let new_fn: Symbol<unsafe extern fn() -> *mut c_void> =
-                lib.get(format!("secret_env_{}_new", env_id).as_bytes())
-                    .expect("Failed to load new function");
-
-            let env_ptr = new_fn();
-
-            SecretEnv {
-                env_ptr,
-                lib,
-                env_id,
-            }
+    pub fn from_default_library(env_id: usize) -> Result<Self, SecretEnvError> {
+        Self::load(Self::default_library_path(), env_id)
+    }
+
+    /// Loads environment `env_id` from the library at `path`, resolving every
+    /// function the environment needs.
+    pub fn load(path: impl AsRef<Path>, env_id: usize) -> Result<Self, SecretEnvError> {
+        let lib = Self::open_library(path.as_ref())?;
+
+        if !Self::exports(&lib, &format!("secret_env_{}_new", env_id)) {
+            return Err(SecretEnvError::UnknownEnv { env_id, available: Self::count_exported(&lib) });
         }
+        let vtable = VTable::resolve(lib, &format!("secret_env_{}_", env_id))
+            .map_err(|symbol| SecretEnvError::MissingSymbol { symbol })?;
+
+        Ok(SecretEnv { env: PluginEnv::from_vtable(Arc::new(vtable)), env_id })
+    }
+
+    /// Number of secret environments the library at `path` exports, numbered from 0.
+    pub fn count_available(path: impl AsRef<Path>) -> Result<usize, SecretEnvError> {
+        Ok(Self::count_exported(&Self::open_library(path.as_ref())?))
     }
-}
 
-impl Drop for SecretEnv {
-    fn drop(&mut self) {
+    pub fn env_id(&self) -> usize {
+        self.env_id
+    }
+
+    fn open_library(path: &Path) -> Result<Library, SecretEnvError> {
         unsafe {
-            if let Ok(delete_fn) = self.lib.get::<unsafe extern fn(*mut c_void)>(
-                format!("secret_env_{}_delete", self.env_id).as_bytes()
-            ) {
-                delete_fn(self.env_ptr);
-            }
+            Library::new(path).map_err(|e| SecretEnvError::Library {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            })
         }
     }
-}
 
-impl Environment for SecretEnv {
-    fn new() -> Self {
-        Self::new(0)  // Default to environment 0
+    fn exports(lib: &Library, symbol: &str) -> bool {
+        unsafe { lib.get::<*const c_void>(symbol.as_bytes()).is_ok() }
     }
 
+    fn count_exported(lib: &Library) -> usize {
+        (0..).take_while(|id| Self::exports(lib, &format!("secret_env_{}_new", id))).count()
+    }
+}
+
+impl Environment for SecretEnv {
     fn num_states(&self) -> usize {
-        unsafe {
-            let num_states_fn: Symbol<unsafe extern fn() -> usize> =
-                self.lib.get(format!("secret_env_{}_num_states", self.env_id).as_bytes())
-                    .expect("Failed to load num_states function");
-            num_states_fn()
-        }
+        self.env.num_states()
     }
 
     fn num_actions(&self) -> usize {
-        unsafe {
-            let num_actions_fn: Symbol<unsafe extern fn() -> usize> =
-                self.lib.get(format!("secret_env_{}_num_actions", self.env_id).as_bytes())
-                    .expect("Failed to load num_actions function");
-            num_actions_fn()
-        }
+        self.env.num_actions()
     }
 
     fn state_id(&self) -> usize {
-        unsafe {
-            let state_id_fn: Symbol<unsafe extern fn(*const c_void) -> usize> =
-                self.lib.get(format!("secret_env_{}_state_id", self.env_id).as_bytes())
-                    .expect("Failed to load state_id function");
-            state_id_fn(self.env_ptr)
-        }
+        self.env.state_id()
     }
 
     fn reset(&mut self) {
-        unsafe {
-            let reset_fn: Symbol<unsafe extern fn(*mut c_void)> =
-                self.lib.get(format!("secret_env_{}_reset", self.env_id).as_bytes())
-                    .expect("Failed to load reset function");
-            reset_fn(self.env_ptr);
-        }
+        self.env.reset();
     }
 
     fn is_game_over(&self) -> bool {
-        unsafe {
-            let is_game_over_fn: Symbol<unsafe extern fn(*const c_void) -> bool> =
-                self.lib.get(format!("secret_env_{}_is_game_over", self.env_id).as_bytes())
-                    .expect("Failed to load is_game_over function");
-            is_game_over_fn(self.env_ptr)
-        }
+        self.env.is_game_over()
     }
 
     fn available_actions(&self) -> Vec<usize> {
-        unsafe {
-            let available_actions_fn: Symbol<unsafe extern fn(*const c_void) -> *const usize> =
-                self.lib.get(format!("secret_env_{}_available_actions", self.env_id).as_bytes())
-                    .expect("Failed to load available_actions function");
+        self.env.available_actions()
+    }
 
-            let available_actions_len_fn: Symbol<unsafe extern fn(*const c_void) -> usize> =
-                self.lib.get(format!("secret_env_{}_available_actions_len", self.env_id).as_bytes())
-                    .expect("Failed to load available_actions_len function");
+    fn score(&self) -> f32 {
+        self.env.score()
+    }
 
-            let actions_ptr = available_actions_fn(self.env_ptr);
-            let len = available_actions_len_fn(self.env_ptr);
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.env.step(action)
+    }
 
-            let mut actions = Vec::with_capacity(len);
-            for i in 0..len {
-                actions.push(*actions_ptr.add(i));
-            }
+    fn display(&self) {
+        self.env.display();
+    }
 
-            let delete_fn: Symbol<unsafe extern fn(*const usize, usize)> =
-                self.lib.get(format!("secret_env_{}_available_actions_delete", self.env_id).as_bytes())
-                    .expect("Failed to load available_actions_delete function");
-            delete_fn(actions_ptr, len);
+    fn fresh(&self) -> Self {
+        SecretEnv { env: self.env.fresh(), env_id: self.env_id }
+    }
 
-            actions
-        }
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(SecretEnv { env: self.env.snapshot()?, env_id: self.env_id })
     }
+}
 
-    fn score(&self) -> f32 {
-        unsafe {
-            let score_fn: Symbol<unsafe extern fn(*const c_void) -> f32> =
-                self.lib.get(format!("secret_env_{}_score", self.env_id).as_bytes())
-                    .expect("Failed to load score function");
-            score_fn(self.env_ptr)
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    const LIBRARY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../libs/libsecret_envs.so");
+
+    #[test]
+    fn test_bundled_library_exports_four_environments() {
+        assert_eq!(SecretEnv::count_available(LIBRARY).unwrap(), 4);
+        let env = SecretEnv::load(LIBRARY, 3).unwrap();
+        assert!(env.num_states() > 0);
+    }
+
+    fn play_randomly(env: &mut SecretEnv, steps: usize, seed: u64) {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        env.reset();
+        for _ in 0..steps {
+            if env.is_game_over() {
+                break;
+            }
+            let action = *env.available_actions().choose(&mut rng).unwrap();
+            env.step(action).unwrap();
         }
     }
 
-    fn step(&mut self, action: usize) {
-        unsafe {
-            let step_fn: Symbol<unsafe extern fn(*mut c_void, usize)> =
-                self.lib.get(format!("secret_env_{}_step", self.env_id).as_bytes())
-                    .expect("Failed to load step function");
-            step_fn(self.env_ptr, action);
+    #[test]
+    fn test_snapshot_replays_the_current_state() {
+        // Environments 0 to 2 are deterministic, so replaying always succeeds
+        for env_id in 0..3 {
+            let mut env = SecretEnv::load(LIBRARY, env_id).unwrap();
+            play_randomly(&mut env, 3, env_id as u64);
+
+            let mut snapshot = env.snapshot().unwrap();
+            assert_eq!(snapshot.state_id(), env.state_id());
+            assert_eq!(snapshot.score(), env.score());
+            assert_eq!(snapshot.available_actions(), env.available_actions());
+
+            // Stepping the snapshot leaves the original untouched
+            let state = env.state_id();
+            if let Some(&action) = snapshot.available_actions().first() {
+                snapshot.step(action).unwrap();
+            }
+            assert_eq!(env.state_id(), state);
         }
     }
 
-    fn display(&self) {
-        unsafe {
-            let display_fn: Symbol<unsafe extern fn(*const c_void)> =
-                self.lib.get(format!("secret_env_{}_display", self.env_id).as_bytes())
-                    .expect("Failed to load display function");
-            display_fn(self.env_ptr);
+    #[test]
+    fn test_stochastic_snapshot_never_diverges_silently() {
+        let mut env = SecretEnv::load(LIBRARY, 3).unwrap();
+        play_randomly(&mut env, 200, 0);
+        match env.snapshot() {
+            Ok(snapshot) => assert_eq!(snapshot.state_id(), env.state_id()),
+            Err(e) => assert!(matches!(e, EnvError::Backend(_))),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_loading_errors() {
+        assert!(matches!(
+            SecretEnv::load(LIBRARY, 4),
+            Err(SecretEnvError::UnknownEnv { env_id: 4, available: 4 })
+        ));
+        assert!(matches!(
+            SecretEnv::load("./missing/libsecret_envs.so", 0),
+            Err(SecretEnvError::Library { .. })
+        ));
+    }
+}
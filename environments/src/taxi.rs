@@ -0,0 +1,292 @@
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError};
+
+const SIZE: usize = 5;
+const NUM_LOCATIONS: usize = 4;
+const IN_TAXI: usize = NUM_LOCATIONS;
+const LOCATIONS: [(usize, usize); NUM_LOCATIONS] = [(0, 0), (0, 4), (4, 0), (4, 3)];  // R, G, Y, B as (row, col)
+
+// Walls between (row, col) and (row, col + 1)
+const WALLS: [(usize, usize); 6] = [(0, 1), (1, 1), (3, 0), (3, 2), (4, 0), (4, 2)];
+
+const STEP_REWARD: f32 = -1.0;
+const ILLEGAL_REWARD: f32 = -10.0;
+const DROPOFF_REWARD: f32 = 20.0;
+
+/// The Taxi domain (Dietterich, 2000).
+///
+/// ```text
+/// +---------+
+/// |R: | : :G|
+/// | : | : : |
+/// | : : : : |
+/// | | : | : |
+/// |Y| : |B: |
+/// +---------+
+/// ```
+///
+/// Actions: South (0), North (1), East (2), West (3), Pickup (4), Dropoff (5).
+/// Every step costs -1, a pickup or dropoff at the wrong place costs -10 and
+/// delivering the passenger gives +20 and ends the episode. States are encoded
+/// as `((row * 5 + col) * 5 + passenger) * 4 + destination`, where passenger 4
+/// means "in the taxi", for 500 states in total.
+#[derive(Clone)]
+pub struct Taxi {
+    taxi_row: usize,
+    taxi_col: usize,
+    passenger: usize,
+    destination: usize,
+    total_reward: f32,
+    delivered: bool,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl Taxi {
+    pub fn new() -> Self {
+        Self::with_seed(42)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let mut env = Taxi {
+            taxi_row: 0,
+            taxi_col: 0,
+            passenger: 0,
+            destination: 1,
+            total_reward: 0.0,
+            delivered: false,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        };
+        env.reset();
+        env
+    }
+
+    pub fn encode(taxi_row: usize, taxi_col: usize, passenger: usize, destination: usize) -> usize {
+        ((taxi_row * SIZE + taxi_col) * (NUM_LOCATIONS + 1) + passenger) * NUM_LOCATIONS + destination
+    }
+
+    /// Decodes a state id into (taxi row, taxi column, passenger, destination).
+    pub fn decode(state: usize) -> (usize, usize, usize, usize) {
+        let destination = state % NUM_LOCATIONS;
+        let state = state / NUM_LOCATIONS;
+        let passenger = state % (NUM_LOCATIONS + 1);
+        let state = state / (NUM_LOCATIONS + 1);
+        (state / SIZE, state % SIZE, passenger, destination)
+    }
+
+    // A state is terminal once the passenger has been dropped at the destination
+    fn is_terminal(passenger: usize, destination: usize) -> bool {
+        passenger == destination
+    }
+
+    fn blocked(row: usize, col: usize) -> bool {
+        WALLS.contains(&(row, col))
+    }
+
+    /// Applies `action` to a decoded state and returns (next state id, reward).
+    fn transition(state: usize, action: usize) -> (usize, f32) {
+        let (mut row, mut col, mut passenger, destination) = Self::decode(state);
+        let mut reward = STEP_REWARD;
+
+        match action {
+            0 => row = (row + 1).min(SIZE - 1),
+            1 => row = row.saturating_sub(1),
+            2 if col < SIZE - 1 && !Self::blocked(row, col) => col += 1,
+            3 if col > 0 && !Self::blocked(row, col - 1) => col -= 1,
+            2 | 3 => {}
+            4 => {
+                if passenger < NUM_LOCATIONS && LOCATIONS[passenger] == (row, col) {
+                    passenger = IN_TAXI;
+                } else {
+                    reward = ILLEGAL_REWARD;
+                }
+            }
+            5 => {
+                if passenger == IN_TAXI && LOCATIONS[destination] == (row, col) {
+                    passenger = destination;
+                    reward = DROPOFF_REWARD;
+                } else {
+                    reward = ILLEGAL_REWARD;
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        (Self::encode(row, col, passenger, destination), reward)
+    }
+}
+
+impl Default for Taxi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for Taxi {
+    fn num_states(&self) -> usize {
+        SIZE * SIZE * (NUM_LOCATIONS + 1) * NUM_LOCATIONS
+    }
+
+    fn num_actions(&self) -> usize {
+        6
+    }
+
+    fn state_id(&self) -> usize {
+        Self::encode(self.taxi_row, self.taxi_col, self.passenger, self.destination)
+    }
+
+    fn reset(&mut self) {
+        self.taxi_row = self.rng.random_range(0..SIZE);
+        self.taxi_col = self.rng.random_range(0..SIZE);
+        self.passenger = self.rng.random_range(0..NUM_LOCATIONS);
+        self.destination = self.rng.random_range(0..NUM_LOCATIONS - 1);
+        if self.destination >= self.passenger {
+            self.destination += 1;
+        }
+        self.total_reward = 0.0;
+        self.delivered = false;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.delivered
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            (0..6).collect()
+        }
+    }
+
+    fn score(&self) -> f32 {
+        self.total_reward
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        let (next_state, reward) = Self::transition(self.state_id(), action);
+        let (row, col, passenger, _) = Self::decode(next_state);
+        self.taxi_row = row;
+        self.taxi_col = col;
+        self.passenger = passenger;
+        self.total_reward += reward;
+        self.delivered = Self::is_terminal(passenger, self.destination);
+        Ok(())
+    }
+
+    fn display(&self) {
+        println!("+---------+");
+        for row in 0..SIZE {
+            print!("|");
+            for col in 0..SIZE {
+                let cell = if (row, col) == (self.taxi_row, self.taxi_col) {
+                    if self.passenger == IN_TAXI { 'T' } else { 't' }
+                } else if let Some(loc) = LOCATIONS.iter().position(|&l| l == (row, col)) {
+                    let name = ['R', 'G', 'Y', 'B'][loc];
+                    if loc == self.passenger { 'P' } else if loc == self.destination { name } else { name.to_ascii_lowercase() }
+                } else {
+                    ' '
+                };
+                print!("{}", cell);
+                if col < SIZE - 1 {
+                    print!("{}", if Self::blocked(row, col) { '|' } else { ':' });
+                }
+            }
+            println!("|");
+        }
+        println!("+---------+");
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        let mut probs = vec![vec![vec![0.0; self.num_states()]; self.num_actions()]; self.num_states()];
+
+        for (state, state_probs) in probs.iter_mut().enumerate() {
+            let (_, _, passenger, destination) = Self::decode(state);
+            // Terminal states have no outgoing transitions
+            if Self::is_terminal(passenger, destination) {
+                continue;
+            }
+            for (action, next_probs) in state_probs.iter_mut().enumerate() {
+                let (next_state, _) = Self::transition(state, action);
+                next_probs[next_state] = 1.0;  // Deterministic transition
+            }
+        }
+
+        probs
+    }
+
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        let mut rewards = vec![vec![0.0; self.num_actions()]; self.num_states()];
+
+        for (state, state_rewards) in rewards.iter_mut().enumerate() {
+            let (_, _, passenger, destination) = Self::decode(state);
+            if Self::is_terminal(passenger, destination) {
+                continue;
+            }
+            for (action, reward) in state_rewards.iter_mut().enumerate() {
+                *reward = Self::transition(state, action).1;
+            }
+        }
+
+        rewards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let env = Taxi::new();
+        for state in 0..env.num_states() {
+            let (row, col, passenger, destination) = Taxi::decode(state);
+            assert_eq!(Taxi::encode(row, col, passenger, destination), state);
+        }
+    }
+
+    #[test]
+    fn test_model_matches_step() {
+        let mut env = Taxi::new();
+        let probs = env.transition_probabilities();
+        let rewards = env.reward_function();
+
+        for _ in 0..200 {
+            env.reset();
+            while !env.is_game_over() {
+                let state = env.state_id();
+                let action = env.rng.random_range(0..6);
+                let score = env.score();
+                env.step(action).unwrap();
+                assert_eq!(probs[state][action][env.state_id()], 1.0);
+                assert_eq!(rewards[state][action], env.score() - score);
+            }
+        }
+    }
+
+    #[test]
+    fn test_walls_block_movement() {
+        // East from (0, 1) hits the wall next to R
+        let (next, reward) = Taxi::transition(Taxi::encode(0, 1, 0, 1), 2);
+        assert_eq!(Taxi::decode(next), (0, 1, 0, 1));
+        assert_eq!(reward, STEP_REWARD);
+    }
+}
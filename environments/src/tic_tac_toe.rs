@@ -0,0 +1,343 @@
+use std::fmt;
+use std::io::{self, Write};
+use rand::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::{Environment, EnvError, PolicyFn};
+
+const EMPTY: u8 = 0;
+const AGENT: u8 = 1;
+const OPPONENT: u8 = 2;
+
+const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2], [3, 4, 5], [6, 7, 8],  // Rows
+    [0, 3, 6], [1, 4, 7], [2, 5, 8],  // Columns
+    [0, 4, 8], [2, 4, 6],             // Diagonals
+];
+
+/// The built-in player the agent is facing.
+#[derive(Clone)]
+pub enum Opponent {
+    Random,
+    Minimax,
+    /// A trained agent. It receives the board from its own point of view,
+    /// so a policy trained as the first player can be reused directly. A
+    /// move to a taken cell fails the agent's step.
+    Policy(PolicyFn),
+}
+
+impl fmt::Debug for Opponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opponent::Random => write!(f, "Random"),
+            Opponent::Minimax => write!(f, "Minimax"),
+            Opponent::Policy(_) => write!(f, "Policy"),
+        }
+    }
+}
+
+/// Tic-Tac-Toe against a built-in opponent. The agent plays X and moves first.
+///
+/// Each cell is encoded in base 3 (0 = empty, 1 = agent, 2 = opponent), giving
+/// 3^9 state ids. Actions are cell indices 0..9, row by row.
+#[derive(Clone)]
+pub struct TicTacToe {
+    board: [u8; 9],
+    opponent: Opponent,
+    winner: Option<u8>,
+    pub human_mode: bool,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl TicTacToe {
+    pub fn new() -> Self {
+        Self::new_with_opponent(Opponent::Random)
+    }
+
+    pub fn new_with_opponent(opponent: Opponent) -> Self {
+        TicTacToe {
+            board: [EMPTY; 9],
+            opponent,
+            winner: None,
+            human_mode: false,
+            rng: Xoshiro256PlusPlus::seed_from_u64(42),
+        }
+    }
+
+    /// In human mode the opponent's moves are read from stdin.
+    pub fn new_with_mode(human_mode: bool) -> Self {
+        TicTacToe {
+            human_mode,
+            ..Self::new_with_opponent(Opponent::Random)
+        }
+    }
+
+    pub fn set_opponent(&mut self, opponent: Opponent) {
+        self.opponent = opponent;
+    }
+
+    fn encode(board: &[u8; 9], player: u8) -> usize {
+        board.iter().rev().fold(0, |acc, &cell| {
+            let cell = match cell {
+                EMPTY => 0,
+                c if c == player => 1,
+                _ => 2,
+            };
+            acc * 3 + cell
+        })
+    }
+
+    fn empty_cells(board: &[u8; 9]) -> Vec<usize> {
+        (0..9).filter(|&i| board[i] == EMPTY).collect()
+    }
+
+    fn find_winner(board: &[u8; 9]) -> Option<u8> {
+        LINES.iter()
+            .find(|line| board[line[0]] != EMPTY && line.iter().all(|&i| board[i] == board[line[0]]))
+            .map(|line| board[line[0]])
+    }
+
+    fn is_full(board: &[u8; 9]) -> bool {
+        board.iter().all(|&cell| cell != EMPTY)
+    }
+
+    // Value of the position for `player` when `player` is to move
+    fn minimax(board: &mut [u8; 9], player: u8) -> i32 {
+        let other = if player == AGENT { OPPONENT } else { AGENT };
+        let mut best = i32::MIN;
+        for cell in Self::empty_cells(board) {
+            board[cell] = player;
+            let value = if Self::find_winner(board).is_some() {
+                1
+            } else if Self::is_full(board) {
+                0
+            } else {
+                -Self::minimax(board, other)
+            };
+            board[cell] = EMPTY;
+            best = best.max(value);
+        }
+        best
+    }
+
+    fn minimax_move(&mut self) -> usize {
+        let mut board = self.board;
+        let mut best_moves = Vec::new();
+        let mut best_value = i32::MIN;
+
+        for cell in Self::empty_cells(&board) {
+            board[cell] = OPPONENT;
+            let value = if Self::find_winner(&board).is_some() {
+                1
+            } else if Self::is_full(&board) {
+                0
+            } else {
+                -Self::minimax(&mut board, AGENT)
+            };
+            board[cell] = EMPTY;
+
+            if value > best_value {
+                best_value = value;
+                best_moves.clear();
+            }
+            if value == best_value {
+                best_moves.push(cell);
+            }
+        }
+
+        // Break ties randomly so the agent does not overfit to a single line
+        *best_moves.choose(&mut self.rng).unwrap()
+    }
+
+    fn get_opponent_move(&mut self) -> Result<usize, EnvError> {
+        let legal = Self::empty_cells(&self.board);
+        if self.human_mode {
+            return Ok(Self::get_human_move(&legal));
+        }
+        match self.opponent.clone() {
+            Opponent::Random => Ok(*legal.choose(&mut self.rng).unwrap()),
+            Opponent::Minimax => Ok(self.minimax_move()),
+            Opponent::Policy(policy) => {
+                let action = policy(Self::encode(&self.board, OPPONENT), &legal);
+                if legal.contains(&action) {
+                    Ok(action)
+                } else {
+                    Err(EnvError::Backend(format!(
+                        "Opponent policy played cell {} but the free cells are {:?}", action, legal
+                    )))
+                }
+            }
+        }
+    }
+
+    fn get_human_move(legal: &[usize]) -> usize {
+        loop {
+            print!("Enter your move (cell 0-8): ");
+            io::stdout().flush().unwrap();
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input).unwrap();
+
+            match input.trim().parse() {
+                Ok(num) if legal.contains(&num) => return num,
+                _ => println!("Invalid input! Please enter one of {:?}.", legal),
+            }
+        }
+    }
+
+    fn update_winner(&mut self) {
+        if let Some(winner) = Self::find_winner(&self.board) {
+            self.winner = Some(winner);
+        } else if Self::is_full(&self.board) {
+            self.winner = Some(EMPTY);  // Draw
+        }
+    }
+}
+
+impl Default for TicTacToe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for TicTacToe {
+    fn num_states(&self) -> usize {
+        3usize.pow(9)
+    }
+
+    fn num_actions(&self) -> usize {
+        9
+    }
+
+    fn state_id(&self) -> usize {
+        Self::encode(&self.board, AGENT)
+    }
+
+    fn reset(&mut self) {
+        self.board = [EMPTY; 9];
+        self.winner = None;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            Self::empty_cells(&self.board)
+        }
+    }
+
+    fn score(&self) -> f32 {
+        match self.winner {
+            Some(AGENT) => 1.0,
+            Some(OPPONENT) => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.check_action(action)?;
+
+        self.board[action] = AGENT;
+        self.update_winner();
+
+        if !self.is_game_over() {
+            if self.human_mode {
+                println!("AI played: {}", action);
+                self.display();
+            }
+            let opponent_move = match self.get_opponent_move() {
+                Ok(cell) => cell,
+                Err(e) => {
+                    // Take the agent's move back so a failed step changes nothing
+                    self.board[action] = EMPTY;
+                    return Err(e);
+                }
+            };
+            self.board[opponent_move] = OPPONENT;
+            self.update_winner();
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        for row in 0..3 {
+            for col in 0..3 {
+                let cell = row * 3 + col;
+                match self.board[cell] {
+                    AGENT => print!("X "),
+                    OPPONENT => print!("O "),
+                    _ => print!("{} ", cell),
+                }
+            }
+            println!();
+        }
+    }
+
+    fn fresh(&self) -> Self {
+        let mut env = self.clone();
+        env.reset();
+        env
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        for _ in 0..stream {
+            self.rng.jump();
+        }
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_state_encoding() {
+        let mut env = TicTacToe::new();
+        assert_eq!(env.state_id(), 0);
+        env.board[0] = AGENT;
+        env.board[8] = OPPONENT;
+        assert_eq!(env.state_id(), 1 + 2 * 3usize.pow(8));
+        assert_eq!(TicTacToe::encode(&env.board, OPPONENT), 2 + 3usize.pow(8));
+    }
+
+    #[test]
+    fn test_minimax_never_loses_against_random() {
+        let mut env = TicTacToe::new_with_opponent(Opponent::Minimax);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        for _ in 0..50 {
+            env.reset();
+            while !env.is_game_over() {
+                let action = *env.available_actions().choose(&mut rng).unwrap();
+                env.step(action).unwrap();
+            }
+            assert!(env.score() <= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_minimax_blocks_immediate_win() {
+        let mut env = TicTacToe::new_with_opponent(Opponent::Minimax);
+        env.board[0] = AGENT;
+        env.board[4] = OPPONENT;
+        env.board[1] = AGENT;
+        assert_eq!(env.minimax_move(), 2);
+    }
+
+    #[test]
+    fn test_illegal_policy_moves_fail_the_step() {
+        // Always plays the first cell, which the agent takes below
+        let mut env = TicTacToe::new_with_opponent(Opponent::Policy(Arc::new(|_, _| 0)));
+        assert!(matches!(env.step(0), Err(EnvError::Backend(_))));
+        assert_eq!(env.state_id(), 0);
+        env.step(4).unwrap();
+        assert_eq!(env.board[0], OPPONENT);
+    }
+}
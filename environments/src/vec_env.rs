@@ -0,0 +1,276 @@
+use std::thread;
+use crate::{Environment, EnvError};
+
+/// What one environment of a `VecEnv` did during a batched step.
+struct Outcome {
+    state: usize,
+    reward: f32,
+    done: bool,
+    final_state: Option<(usize, Vec<f32>)>,
+    episode_return: Option<f32>,
+}
+
+/// Result of stepping every environment of a `VecEnv` once. Entry `i` of each
+/// vector belongs to environment `i`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepBatch {
+    /// State after the step, or after the automatic reset when `dones[i]`.
+    pub states: Vec<usize>,
+    /// Change in score caused by the step.
+    pub rewards: Vec<f32>,
+    /// Whether the step ended the episode, in a terminal state or not.
+    pub dones: Vec<bool>,
+    /// Whether the episode that just ended was cut short by a step limit
+    /// rather than terminated, so its last state still has a value.
+    pub truncated: Vec<bool>,
+    /// State id and observation a truncated episode ended in, before the
+    /// reset, for learners to bootstrap from.
+    pub final_states: Vec<Option<(usize, Vec<f32>)>>,
+    /// Undiscounted return of the episode that just ended, if any.
+    pub episode_returns: Vec<Option<f32>>,
+}
+
+type StepAll<T> = fn(&mut [T], &mut [f32], &[usize], usize) -> Vec<Result<Outcome, EnvError>>;
+
+/// N independent copies of an environment stepped in lockstep, for batched
+/// rollouts. Environments that finish an episode are reset right away, so
+/// every call to `step` takes one action per environment.
+pub struct VecEnv<T: Environment> {
+    envs: Vec<T>,
+    returns: Vec<f32>,
+    workers: usize,
+    step_all: StepAll<T>,
+}
+
+impl<T: Environment> VecEnv<T> {
+    /// `num_envs` fresh copies of `env`, stepped one after the other. Each
+    /// copy draws from its own random stream, the first one from `env`'s.
+    pub fn new(env: &T, num_envs: usize) -> Self {
+        assert!(num_envs > 0, "A VecEnv needs at least one environment");
+        let envs = (0..num_envs)
+            .map(|i| {
+                let mut copy = env.fresh();
+                if i > 0 {
+                    copy.split_rng(i);
+                    copy.reset();
+                }
+                copy
+            })
+            .collect();
+        VecEnv {
+            envs,
+            returns: vec![0.0; num_envs],
+            workers: 1,
+            step_all: step_sequential::<T>,
+        }
+    }
+
+    pub fn num_envs(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.envs[0].num_states()
+    }
+
+    pub fn num_actions(&self) -> usize {
+        self.envs[0].num_actions()
+    }
+
+    pub fn envs(&self) -> &[T] {
+        &self.envs
+    }
+
+    pub fn states(&self) -> Vec<usize> {
+        self.envs.iter().map(|env| env.state_id()).collect()
+    }
+
+    pub fn observations(&self) -> Vec<Vec<f32>> {
+        self.envs.iter().map(|env| env.observation()).collect()
+    }
+
+    pub fn available_actions(&self) -> Vec<Vec<usize>> {
+        self.envs.iter().map(|env| env.available_actions()).collect()
+    }
+
+    /// Starts a new episode in every environment.
+    pub fn reset(&mut self) {
+        for env in &mut self.envs {
+            env.reset();
+        }
+        self.returns.iter_mut().for_each(|r| *r = 0.0);
+    }
+
+    /// Plays `actions[i]` in environment `i`. If any environment fails, the
+    /// steps the others took are lost: every environment is reset before the
+    /// first error is returned, so the next call starts new episodes instead
+    /// of continuing ones whose returns were only partly reported.
+    pub fn step(&mut self, actions: &[usize]) -> Result<StepBatch, EnvError> {
+        assert_eq!(actions.len(), self.envs.len(), "One action per environment is needed");
+        let outcomes = (self.step_all)(&mut self.envs, &mut self.returns, actions, self.workers);
+        if let Some(error) = outcomes.iter().find_map(|outcome| outcome.as_ref().err()) {
+            let error = error.clone();
+            self.reset();
+            return Err(error);
+        }
+
+        let mut batch = StepBatch {
+            states: Vec::with_capacity(outcomes.len()),
+            rewards: Vec::with_capacity(outcomes.len()),
+            dones: Vec::with_capacity(outcomes.len()),
+            truncated: Vec::with_capacity(outcomes.len()),
+            final_states: Vec::with_capacity(outcomes.len()),
+            episode_returns: Vec::with_capacity(outcomes.len()),
+        };
+        for outcome in outcomes.into_iter().flatten() {
+            batch.states.push(outcome.state);
+            batch.rewards.push(outcome.reward);
+            batch.dones.push(outcome.done);
+            batch.truncated.push(outcome.final_state.is_some());
+            batch.final_states.push(outcome.final_state);
+            batch.episode_returns.push(outcome.episode_return);
+        }
+        Ok(batch)
+    }
+}
+
+impl<T: Environment + Send> VecEnv<T> {
+    /// Like `new`, but `step` splits the environments between `workers` threads.
+    pub fn with_workers(env: &T, num_envs: usize, workers: usize) -> Self {
+        assert!(workers > 0, "A VecEnv needs at least one worker");
+        VecEnv {
+            workers: workers.min(num_envs),
+            step_all: step_parallel::<T>,
+            ..Self::new(env, num_envs)
+        }
+    }
+}
+
+fn step_one<T: Environment>(env: &mut T, episode_return: &mut f32, action: usize) -> Result<Outcome, EnvError> {
+    let prev_score = env.score();
+    env.step(action)?;
+    let reward = env.score() - prev_score;
+    *episode_return += reward;
+
+    let done = env.is_game_over();
+    let final_state = if done && !env.terminated() {
+        Some((env.state_id(), env.observation()))
+    } else {
+        None
+    };
+    let finished = if done {
+        env.reset();
+        Some(std::mem::take(episode_return))
+    } else {
+        None
+    };
+    Ok(Outcome { state: env.state_id(), reward, done, final_state, episode_return: finished })
+}
+
+fn step_sequential<T: Environment>(
+    envs: &mut [T],
+    returns: &mut [f32],
+    actions: &[usize],
+    _workers: usize,
+) -> Vec<Result<Outcome, EnvError>> {
+    envs.iter_mut()
+        .zip(returns.iter_mut())
+        .zip(actions)
+        .map(|((env, episode_return), &action)| step_one(env, episode_return, action))
+        .collect()
+}
+
+fn step_parallel<T: Environment + Send>(
+    envs: &mut [T],
+    returns: &mut [f32],
+    actions: &[usize],
+    workers: usize,
+) -> Vec<Result<Outcome, EnvError>> {
+    let chunk = envs.len().div_ceil(workers);
+    thread::scope(|scope| {
+        let handles: Vec<_> = envs.chunks_mut(chunk)
+            .zip(returns.chunks_mut(chunk))
+            .zip(actions.chunks(chunk))
+            .map(|((envs, returns), actions)| {
+                scope.spawn(move || step_sequential(envs, returns, actions, 1))
+            })
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cart_pole::CartPole;
+    use crate::line_world::LineWorld;
+    use crate::wrappers::TimeLimit;
+
+    #[test]
+    fn test_finished_environments_reset_automatically() {
+        let mut envs = VecEnv::new(&LineWorld::new(), 2);
+        envs.step(&[1, 0]).unwrap();
+        let batch = envs.step(&[1, 1]).unwrap();
+
+        assert_eq!(batch.dones, vec![true, false]);
+        assert_eq!(batch.rewards, vec![1.0, 0.0]);
+        assert_eq!(batch.episode_returns, vec![Some(1.0), None]);
+        // The first environment is back at the start, the second one moved back to it
+        assert_eq!(batch.states, vec![2, 2]);
+        assert_eq!(envs.available_actions(), vec![vec![0, 1], vec![0, 1]]);
+    }
+
+    #[test]
+    fn test_truncated_episodes_keep_their_last_state() {
+        let mut envs = VecEnv::new(&TimeLimit::with_max_steps(LineWorld::new(), 2), 2);
+        envs.step(&[1, 1]).unwrap();
+        let batch = envs.step(&[1, 0]).unwrap();
+
+        // The first one reached the right end, the second one ran out of steps back in the middle
+        assert_eq!(batch.dones, vec![true, true]);
+        assert_eq!(batch.truncated, vec![false, true]);
+        assert_eq!(batch.final_states, vec![None, Some((2, LineWorld::new().observation()))]);
+        assert_eq!(batch.states, vec![2, 2]);
+    }
+
+    #[test]
+    fn test_copies_of_a_seeded_environment_differ() {
+        let envs = VecEnv::new(&CartPole::with_seed(3), 2);
+        assert_ne!(envs.envs()[0].observation(), envs.envs()[1].observation());
+        // The first copy still follows the seed it was given
+        assert_eq!(envs.envs()[0].observation(), CartPole::with_seed(3).fresh().observation());
+    }
+
+    #[test]
+    fn test_workers_match_sequential_stepping() {
+        let mut sequential = VecEnv::new(&LineWorld::new(), 5);
+        let mut threaded = VecEnv::with_workers(&LineWorld::new(), 5, 2);
+
+        for actions in [[0, 1, 0, 1, 1], [0, 1, 1, 0, 1], [1, 1, 0, 0, 0]] {
+            assert_eq!(sequential.step(&actions).unwrap(), threaded.step(&actions).unwrap());
+        }
+        assert_eq!(sequential.states(), threaded.states());
+    }
+
+    #[test]
+    fn test_errors_are_surfaced() {
+        let mut envs = VecEnv::with_workers(&LineWorld::new(), 3, 3);
+        assert_eq!(
+            envs.step(&[0, 7, 1]),
+            Err(EnvError::IllegalAction { action: 7, legal: vec![0, 1] })
+        );
+    }
+
+    #[test]
+    fn test_errors_start_new_episodes_everywhere() {
+        let mut envs = VecEnv::new(&LineWorld::new(), 3);
+        envs.step(&[0, 0, 0]).unwrap();
+        assert!(envs.step(&[1, 7, 0]).is_err());
+        assert_eq!(envs.states(), vec![2, 2, 2]);
+
+        let batch = envs.step(&[1, 1, 1]).unwrap();
+        assert_eq!(batch.dones, vec![false, false, false]);
+        let batch = envs.step(&[1, 1, 1]).unwrap();
+        assert_eq!(batch.episode_returns, vec![Some(1.0), Some(1.0), Some(1.0)]);
+    }
+}
@@ -0,0 +1,637 @@
+//! Wrappers that change how an environment plays without touching it or the
+//! learners: step limits, reward transformations and episode statistics.
+//! They compose, e.g. `StepPenalty::with_penalty(TimeLimit::with_max_steps(env, 100), 0.1)`.
+//!
+//! Rewards in this workspace are differences of `score` between steps, so the
+//! reward wrappers adjust `score` by the running total of what they add.
+
+use std::sync::Arc;
+use crate::{Environment, EnvError};
+
+/// Ends episodes after `max_steps` steps, even if the wrapped environment is
+/// not over yet. `truncated` tells the two endings apart.
+#[derive(Clone)]
+pub struct TimeLimit<E: Environment> {
+    env: E,
+    max_steps: usize,
+    steps: usize,
+}
+
+impl<E: Environment> TimeLimit<E> {
+    pub fn with_max_steps(env: E, max_steps: usize) -> Self {
+        TimeLimit { env, max_steps, steps: 0 }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+}
+
+impl<E: Environment> Environment for TimeLimit<E> {
+    fn num_states(&self) -> usize {
+        self.env.num_states()
+    }
+
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    fn state_id(&self) -> usize {
+        self.env.state_id()
+    }
+
+    fn reset(&mut self) {
+        self.env.reset();
+        self.steps = 0;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.env.is_game_over() || self.steps >= self.max_steps
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.env.available_actions()
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        self.env.observation()
+    }
+
+    fn score(&self) -> f32 {
+        self.env.score()
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        if self.truncated() {
+            return Err(EnvError::GameOver);
+        }
+        self.env.step(action)?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn display(&self) {
+        self.env.display();
+        println!("Step {}/{}", self.steps, self.max_steps);
+    }
+
+    fn truncated(&self) -> bool {
+        self.env.truncated() || (self.steps >= self.max_steps && !self.env.is_game_over())
+    }
+
+    fn fresh(&self) -> Self {
+        TimeLimit { env: self.env.fresh(), steps: 0, ..*self }
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        self.env.split_rng(stream);
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(TimeLimit { env: self.env.snapshot()?, ..*self })
+    }
+
+    // The step count is not part of the state, so the models are the wrapped ones
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        self.env.transition_probabilities()
+    }
+
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        self.env.reward_function()
+    }
+}
+
+/// Multiplies every reward by `scale`.
+#[derive(Clone)]
+pub struct RewardScale<E: Environment> {
+    env: E,
+    scale: f32,
+}
+
+impl<E: Environment> RewardScale<E> {
+    pub fn with_scale(env: E, scale: f32) -> Self {
+        RewardScale { env, scale }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+}
+
+impl<E: Environment> Environment for RewardScale<E> {
+    fn num_states(&self) -> usize {
+        self.env.num_states()
+    }
+
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    fn state_id(&self) -> usize {
+        self.env.state_id()
+    }
+
+    fn reset(&mut self) {
+        self.env.reset();
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.env.is_game_over()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.env.available_actions()
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        self.env.observation()
+    }
+
+    fn score(&self) -> f32 {
+        self.env.score() * self.scale
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.env.step(action)
+    }
+
+    fn display(&self) {
+        self.env.display();
+    }
+
+    fn truncated(&self) -> bool {
+        self.env.truncated()
+    }
+
+    fn fresh(&self) -> Self {
+        RewardScale { env: self.env.fresh(), ..*self }
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        self.env.split_rng(stream);
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(RewardScale { env: self.env.snapshot()?, ..*self })
+    }
+
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        self.env.transition_probabilities()
+    }
+
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        self.env.reward_function()
+            .into_iter()
+            .map(|rewards| rewards.into_iter().map(|r| r * self.scale).collect())
+            .collect()
+    }
+}
+
+/// A potential over state ids, used by `RewardShaping`.
+pub type PotentialFn = Arc<dyn Fn(usize) -> f32 + Send + Sync>;
+
+/// Potential-based reward shaping: each step earns `gamma * Φ(s') - Φ(s)` on
+/// top of the wrapped reward, with `Φ` taken as 0 in terminal states. This
+/// speeds up learning without changing which policies are optimal, as long
+/// as `gamma` is the learner's discount factor. Episodes cut short by a
+/// `TimeLimit` inside this wrapper keep the potential of their last state,
+/// since that state is not terminal.
+#[derive(Clone)]
+pub struct RewardShaping<E: Environment> {
+    env: E,
+    potential: PotentialFn,
+    gamma: f32,
+    bonus: f32,
+}
+
+impl<E: Environment> RewardShaping<E> {
+    pub fn with_potential(env: E, gamma: f32, potential: impl Fn(usize) -> f32 + Send + Sync + 'static) -> Self {
+        RewardShaping { env, potential: Arc::new(potential), gamma, bonus: 0.0 }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+
+    fn current_potential(&self) -> f32 {
+        if self.env.terminated() {
+            0.0
+        } else {
+            (self.potential)(self.env.state_id())
+        }
+    }
+}
+
+impl<E: Environment> Environment for RewardShaping<E> {
+    fn num_states(&self) -> usize {
+        self.env.num_states()
+    }
+
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    fn state_id(&self) -> usize {
+        self.env.state_id()
+    }
+
+    fn reset(&mut self) {
+        self.env.reset();
+        self.bonus = 0.0;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.env.is_game_over()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.env.available_actions()
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        self.env.observation()
+    }
+
+    fn score(&self) -> f32 {
+        self.env.score() + self.bonus
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        let before = self.current_potential();
+        self.env.step(action)?;
+        self.bonus += self.gamma * self.current_potential() - before;
+        Ok(())
+    }
+
+    fn display(&self) {
+        self.env.display();
+    }
+
+    fn truncated(&self) -> bool {
+        self.env.truncated()
+    }
+
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        self.env.transition_probabilities()
+    }
+
+    // The expected bonus of each pair. Models do not mark terminal states,
+    // so Φ is used as given there: make it 0 in terminal states for the
+    // model to match the simulated rewards.
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        let transitions = self.env.transition_probabilities();
+        let mut rewards = self.env.reward_function();
+        for (state, (state_rewards, state_transitions)) in rewards.iter_mut().zip(&transitions).enumerate() {
+            for (reward, next_probs) in state_rewards.iter_mut().zip(state_transitions) {
+                *reward += next_probs.iter()
+                    .enumerate()
+                    .map(|(next, &p)| p * (self.gamma * (self.potential)(next) - (self.potential)(state)))
+                    .sum::<f32>();
+            }
+        }
+        rewards
+    }
+
+    fn fresh(&self) -> Self {
+        RewardShaping {
+            env: self.env.fresh(),
+            potential: self.potential.clone(),
+            gamma: self.gamma,
+            bonus: 0.0,
+        }
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        self.env.split_rng(stream);
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(RewardShaping {
+            env: self.env.snapshot()?,
+            potential: self.potential.clone(),
+            gamma: self.gamma,
+            bonus: self.bonus,
+        })
+    }
+}
+
+/// Subtracts `penalty` from the reward of every step, to favour short episodes.
+#[derive(Clone)]
+pub struct StepPenalty<E: Environment> {
+    env: E,
+    penalty: f32,
+    steps: usize,
+}
+
+impl<E: Environment> StepPenalty<E> {
+    pub fn with_penalty(env: E, penalty: f32) -> Self {
+        StepPenalty { env, penalty, steps: 0 }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+}
+
+impl<E: Environment> Environment for StepPenalty<E> {
+    fn num_states(&self) -> usize {
+        self.env.num_states()
+    }
+
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    fn state_id(&self) -> usize {
+        self.env.state_id()
+    }
+
+    fn reset(&mut self) {
+        self.env.reset();
+        self.steps = 0;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.env.is_game_over()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.env.available_actions()
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        self.env.observation()
+    }
+
+    fn score(&self) -> f32 {
+        self.env.score() - self.penalty * self.steps as f32
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.env.step(action)?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn display(&self) {
+        self.env.display();
+    }
+
+    fn truncated(&self) -> bool {
+        self.env.truncated()
+    }
+
+    fn fresh(&self) -> Self {
+        StepPenalty { env: self.env.fresh(), steps: 0, ..*self }
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        self.env.split_rng(stream);
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(StepPenalty { env: self.env.snapshot()?, ..*self })
+    }
+
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        self.env.transition_probabilities()
+    }
+
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        self.env.reward_function()
+            .into_iter()
+            .map(|rewards| rewards.into_iter().map(|r| r - self.penalty).collect())
+            .collect()
+    }
+}
+
+/// Return and length of one finished episode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EpisodeRecord {
+    pub episode_return: f32,
+    pub length: usize,
+}
+
+/// Records the return and length of every episode played through it.
+#[derive(Clone)]
+pub struct EpisodeStats<E: Environment> {
+    env: E,
+    start_score: f32,
+    length: usize,
+    episodes: Vec<EpisodeRecord>,
+}
+
+impl<E: Environment> EpisodeStats<E> {
+    pub fn recording(env: E) -> Self {
+        let start_score = env.score();
+        EpisodeStats { env, start_score, length: 0, episodes: Vec::new() }
+    }
+
+    /// Finished episodes, oldest first.
+    pub fn episodes(&self) -> &[EpisodeRecord] {
+        &self.episodes
+    }
+
+    /// Mean return of the last `last` finished episodes, if there are any.
+    pub fn mean_return(&self, last: usize) -> Option<f32> {
+        let recent = &self.episodes[self.episodes.len().saturating_sub(last)..];
+        if recent.is_empty() {
+            return None;
+        }
+        Some(recent.iter().map(|e| e.episode_return).sum::<f32>() / recent.len() as f32)
+    }
+
+    /// Mean length of the last `last` finished episodes, if there are any.
+    pub fn mean_length(&self, last: usize) -> Option<f32> {
+        let recent = &self.episodes[self.episodes.len().saturating_sub(last)..];
+        if recent.is_empty() {
+            return None;
+        }
+        Some(recent.iter().map(|e| e.length).sum::<usize>() as f32 / recent.len() as f32)
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+}
+
+impl<E: Environment> Environment for EpisodeStats<E> {
+    fn num_states(&self) -> usize {
+        self.env.num_states()
+    }
+
+    fn num_actions(&self) -> usize {
+        self.env.num_actions()
+    }
+
+    fn state_id(&self) -> usize {
+        self.env.state_id()
+    }
+
+    fn reset(&mut self) {
+        self.env.reset();
+        self.start_score = self.env.score();
+        self.length = 0;
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.env.is_game_over()
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        self.env.available_actions()
+    }
+
+    fn observation(&self) -> Vec<f32> {
+        self.env.observation()
+    }
+
+    fn score(&self) -> f32 {
+        self.env.score()
+    }
+
+    fn step(&mut self, action: usize) -> Result<(), EnvError> {
+        self.env.step(action)?;
+        self.length += 1;
+        if self.env.is_game_over() {
+            self.episodes.push(EpisodeRecord {
+                episode_return: self.env.score() - self.start_score,
+                length: self.length,
+            });
+        }
+        Ok(())
+    }
+
+    fn display(&self) {
+        self.env.display();
+    }
+
+    fn truncated(&self) -> bool {
+        self.env.truncated()
+    }
+
+    // The recorded episodes carry over, as they describe past episodes only
+    fn fresh(&self) -> Self {
+        let env = self.env.fresh();
+        EpisodeStats { start_score: env.score(), env, length: 0, episodes: self.episodes.clone() }
+    }
+
+    fn split_rng(&mut self, stream: usize) {
+        self.env.split_rng(stream);
+    }
+
+    fn snapshot(&self) -> Result<Self, EnvError> {
+        Ok(EpisodeStats {
+            env: self.env.snapshot()?,
+            start_score: self.start_score,
+            length: self.length,
+            episodes: self.episodes.clone(),
+        })
+    }
+
+    fn transition_probabilities(&self) -> Vec<Vec<Vec<f32>>> {
+        self.env.transition_probabilities()
+    }
+
+    fn reward_function(&self) -> Vec<Vec<f32>> {
+        self.env.reward_function()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_world::LineWorld;
+    use crate::grid_world::GridWorld;
+
+    #[test]
+    fn test_time_limit_truncates_episodes() {
+        let mut env = TimeLimit::with_max_steps(LineWorld::new(), 2);
+        env.step(0).unwrap();
+        assert!(!env.is_game_over());
+        env.step(1).unwrap();
+
+        assert!(env.is_game_over());
+        assert!(env.truncated());
+        assert!(!env.terminated());
+        // Still there to bootstrap from, but not to play
+        assert_eq!(env.available_actions(), vec![0, 1]);
+        assert_eq!(env.step(1), Err(EnvError::GameOver));
+
+        env.reset();
+        env.step(1).unwrap();
+        env.step(1).unwrap();
+        assert!(env.terminated());
+        assert!(env.available_actions().is_empty());
+    }
+
+    #[test]
+    fn test_reward_wrappers_compose() {
+        let mut env = StepPenalty::with_penalty(RewardScale::with_scale(LineWorld::new(), 10.0), 0.5);
+        env.step(1).unwrap();
+        assert_eq!(env.score(), -0.5);
+        env.step(1).unwrap();
+        assert_eq!(env.score(), 9.0);
+        env.reset();
+        assert_eq!(env.score(), 0.0);
+    }
+
+    #[test]
+    fn test_potential_shaping_telescopes() {
+        // Potential growing towards the winning end of the line
+        let mut env = RewardShaping::with_potential(LineWorld::new(), 1.0, |s| s as f32);
+        env.step(1).unwrap();
+        assert_eq!(env.score(), 1.0);
+        env.step(0).unwrap();
+        assert_eq!(env.score(), 0.0);
+        // With gamma = 1 the bonus cancels out over an episode: only Φ(start) is lost
+        env.step(1).unwrap();
+        env.step(1).unwrap();
+        assert_eq!(env.score(), 1.0 - 2.0);
+    }
+
+    #[test]
+    fn test_shaping_keeps_the_potential_of_truncated_episodes() {
+        let mut env = RewardShaping::with_potential(TimeLimit::with_max_steps(LineWorld::new(), 1), 1.0, |s| s as f32);
+        env.step(1).unwrap();
+        assert!(env.truncated());
+        // Φ(3) - Φ(2), as the episode stopped in a non-terminal state
+        assert_eq!(env.score(), 1.0);
+        assert_eq!(env.fresh().score(), 0.0);
+    }
+
+    #[test]
+    fn test_shaped_model_matches_the_simulated_bonus() {
+        let env = RewardShaping::with_potential(GridWorld::new(), 0.9, |s| s as f32 / 10.0);
+        let plain = GridWorld::new().reward_function();
+        let shaped = env.reward_function();
+        assert_eq!(env.transition_probabilities(), GridWorld::new().transition_probabilities());
+        // From (1, 1) the first action moves up to (1, 0)
+        let mut sim = env.fresh();
+        let state = sim.state_id();
+        sim.step(0).unwrap();
+        assert!((shaped[state][0] - plain[state][0] - sim.score()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_episode_stats_records_finished_episodes() {
+        let mut env = EpisodeStats::recording(TimeLimit::with_max_steps(LineWorld::new(), 10));
+        for actions in [vec![1, 1], vec![0, 1, 0, 0]] {
+            env.reset();
+            for action in actions {
+                env.step(action).unwrap();
+            }
+        }
+
+        assert_eq!(env.episodes(), &[
+            EpisodeRecord { episode_return: 1.0, length: 2 },
+            EpisodeRecord { episode_return: -1.0, length: 4 },
+        ]);
+        assert_eq!(env.mean_return(10), Some(0.0));
+        assert_eq!(env.mean_length(1), Some(4.0));
+    }
+}
@@ -0,0 +1,154 @@
+//! Line World as a plugin environment, written against the C ABI documented
+//! in `environments::plugin` and loaded with `PluginEnv::load`.
+//!
+//! Five cells, the agent starts in the middle and moves left (0) or right
+//! (1). Reaching the left end scores -1, the right end +1.
+
+use std::ffi::c_void;
+
+const ABI_VERSION: u32 = 1;
+// Makes the plugin claim another ABI version, to test that hosts reject it
+const ABI_VERSION_VAR: &str = "LINE_WORLD_PLUGIN_ABI_VERSION";
+const NUM_CELLS: usize = 5;
+const START: usize = 2;
+
+#[derive(Clone)]
+struct LineWorld {
+    pos: usize,
+}
+
+impl LineWorld {
+    fn is_game_over(&self) -> bool {
+        self.pos == 0 || self.pos == NUM_CELLS - 1
+    }
+
+    fn available_actions(&self) -> Vec<usize> {
+        if self.is_game_over() {
+            vec![]
+        } else {
+            vec![0, 1]
+        }
+    }
+}
+
+unsafe fn env<'a>(env: *const c_void) -> &'a LineWorld {
+    &*(env as *const LineWorld)
+}
+
+unsafe fn env_mut<'a>(env: *mut c_void) -> &'a mut LineWorld {
+    &mut *(env as *mut LineWorld)
+}
+
+#[no_mangle]
+pub extern "C" fn plugin_env_abi_version() -> u32 {
+    std::env::var(ABI_VERSION_VAR)
+        .ok()
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(ABI_VERSION)
+}
+
+#[no_mangle]
+pub extern "C" fn plugin_env_new() -> *mut c_void {
+    Box::into_raw(Box::new(LineWorld { pos: START })) as *mut c_void
+}
+
+/// # Safety
+/// `env` must come from `plugin_env_new` or `plugin_env_clone` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_delete(env: *mut c_void) {
+    drop(Box::from_raw(env as *mut LineWorld));
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_clone(env: *const c_void) -> *mut c_void {
+    Box::into_raw(Box::new(self::env(env).clone())) as *mut c_void
+}
+
+#[no_mangle]
+pub extern "C" fn plugin_env_num_states() -> usize {
+    NUM_CELLS
+}
+
+#[no_mangle]
+pub extern "C" fn plugin_env_num_actions() -> usize {
+    2
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_state_id(env: *const c_void) -> usize {
+    self::env(env).pos
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_reset(env: *mut c_void) {
+    env_mut(env).pos = START;
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_is_game_over(env: *const c_void) -> bool {
+    self::env(env).is_game_over()
+}
+
+/// # Safety
+/// `env` must be a live environment. The array must be released with
+/// `plugin_env_available_actions_delete`.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_available_actions(env: *const c_void) -> *const usize {
+    let actions = self::env(env).available_actions().into_boxed_slice();
+    Box::into_raw(actions) as *const usize
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_available_actions_len(env: *const c_void) -> usize {
+    self::env(env).available_actions().len()
+}
+
+/// # Safety
+/// `actions` and `len` must come from `plugin_env_available_actions` and
+/// `plugin_env_available_actions_len` on the same state.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_available_actions_delete(actions: *const usize, len: usize) {
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(actions as *mut usize, len)));
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_score(env: *const c_void) -> f32 {
+    match self::env(env).pos {
+        0 => -1.0,
+        pos if pos == NUM_CELLS - 1 => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// # Safety
+/// `env` must be a live environment and `action` one of its available actions.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_step(env: *mut c_void, action: usize) {
+    let env = env_mut(env);
+    match action {
+        0 => env.pos -= 1,
+        1 => env.pos += 1,
+        _ => unreachable!(),
+    }
+}
+
+/// # Safety
+/// `env` must be a live environment.
+#[no_mangle]
+pub unsafe extern "C" fn plugin_env_display(env: *const c_void) {
+    let pos = self::env(env).pos;
+    let line: String = (0..NUM_CELLS).map(|s| if s == pos { 'X' } else { '_' }).collect();
+    println!("{}", line);
+}
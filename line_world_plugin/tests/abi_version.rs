@@ -0,0 +1,24 @@
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::PathBuf;
+use environments::plugin::{PluginEnv, PluginError, PLUGIN_ABI_VERSION};
+
+// A separate test binary, since the version override is process-wide
+fn plugin_path() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let name = format!("{}line_world_plugin{}", DLL_PREFIX, DLL_SUFFIX);
+    [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("the line_world_plugin cdylib was not built")
+}
+
+#[test]
+fn test_other_abi_versions_are_rejected() {
+    let found = PLUGIN_ABI_VERSION + 1;
+    std::env::set_var("LINE_WORLD_PLUGIN_ABI_VERSION", found.to_string());
+    assert!(matches!(
+        PluginEnv::load(plugin_path()),
+        Err(PluginError::Version { found: f, expected: PLUGIN_ABI_VERSION }) if f == found
+    ));
+}
@@ -0,0 +1,62 @@
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::PathBuf;
+use environments::line_world::LineWorld;
+use environments::plugin::{PluginEnv, PluginError};
+use environments::Environment;
+
+// Cargo builds the cdylib next to the test binary's directory, in target/<profile>
+fn plugin_path() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let name = format!("{}line_world_plugin{}", DLL_PREFIX, DLL_SUFFIX);
+    [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("the line_world_plugin cdylib was not built")
+}
+
+#[test]
+fn test_plugin_plays_like_native_line_world() {
+    let mut plugin = PluginEnv::load(plugin_path()).unwrap();
+    let mut native = LineWorld::new();
+    assert_eq!(plugin.num_states(), native.num_states());
+    assert_eq!(plugin.num_actions(), native.num_actions());
+
+    for actions in [[1, 1, 0, 0], [0, 0, 1, 1], [1, 0, 1, 1]] {
+        plugin.reset();
+        native.reset();
+        for action in actions {
+            if native.is_game_over() {
+                assert!(plugin.step(action).is_err());
+                break;
+            }
+            plugin.step(action).unwrap();
+            native.step(action).unwrap();
+            assert_eq!(plugin.state_id(), native.state_id());
+            assert_eq!(plugin.available_actions(), native.available_actions());
+            assert_eq!(plugin.is_game_over(), native.is_game_over());
+            assert_eq!(plugin.score(), native.score());
+        }
+    }
+}
+
+#[test]
+fn test_plugin_snapshot_is_independent() {
+    let mut env = PluginEnv::load(plugin_path()).unwrap();
+    env.step(1).unwrap();
+
+    let mut snapshot = env.snapshot().unwrap();
+    assert_eq!(snapshot.state_id(), 3);
+    snapshot.step(1).unwrap();
+    assert!(snapshot.is_game_over());
+    assert_eq!(env.state_id(), 3);
+    assert_eq!(env.fresh().state_id(), 2);
+}
+
+#[test]
+fn test_loading_errors() {
+    assert!(matches!(
+        PluginEnv::load("./missing/libplugin.so"),
+        Err(PluginError::Library { .. })
+    ));
+}
@@ -9,37 +9,38 @@ use algorithms::{
     sarsa::Sarsa,
     reinforce::Reinforce,
     semi_gradient_sarsa::SemiGradientSarsa,
-    dqn::DQN
+    dqn::DQN,
+    sarsa_lambda::{SarsaLambda, TraceKind},
+    q_lambda::QLambda,
+    n_step_sarsa::{NStepSarsa, NStepBackup},
+    trained_ai::TrainedAI
 };
 
 use environments::{
     Environment,
+    EnvError,
     line_world::LineWorld,
     grid_world::GridWorld,
-    rps::RPS,
+    rps::{RPS, WinStayLoseShift},
     secret_env::SecretEnv,
-    monty_hall_paradox1::MontyHall,
-    monty_hall_paradox2::MontyHall2
+    monty_hall_paradox1::{MontyHall, Visibility},
+    monty_hall_paradox2::MontyHall2,
+    monty_hall::MontyHallN,
+    belief::BeliefState,
+    wrappers::{StepPenalty, TimeLimit},
+    remote::{EnvServer, RemoteEnv},
+    blackjack::Blackjack,
+    tic_tac_toe::TicTacToe,
+    connect_four::{ConnectFour, Opponent as ConnectFourOpponent},
+    taxi::Taxi,
+    mountain_car::MountainCar,
+    cart_pole::CartPole
 };
 
 use std::io::{self, Write};
-use serde::{Serialize, Deserialize};
-use std::fs;
-use std::path::Path;
-
-#[derive(Serialize, Deserialize)]
-pub enum TrainedAI {
-    QLearning(QLearning),
-    DynaQ(DynaQ),
-    PolicyIteration(PolicyIteration),
-    ValueIteration(ValueIteration),
-    MonteCarloControl(MonteCarloControl),
-    OffPolicyMonteCarloControl(OffPolicyMonteCarloControl),
-    Sarsa(Sarsa),
-    Reinforce(Reinforce),
-    SemiGradientSarsa(SemiGradientSarsa),
-    DQN(DQN),
-}
+
+/// Where `serve` listens and the remote environment menu entry connects.
+const REMOTE_ENV_ADDR: &str = "127.0.0.1:7878";
 
 const ALPHA: f32 = 0.01;
 const EPSILON: f32 = 0.01;
@@ -59,52 +60,42 @@ const ALPHA_SEMI_GRADIENT_SARSA: f32 = 0.1;
 const EPSILON_SEMI_GRADIENT_SARSA: f32 = 0.1;
 const GAMMA_SEMI_GRADIENT_SARSA: f32 = 0.99;
 
+// Episode caps and penalties applied by wrapping the training environment
+const MAX_STEPS_SARSA: usize = 25;
+const MAX_STEPS_SEMI_GRADIENT_SARSA: usize = 25;
+const MAX_STEPS_SARSA_LAMBDA: usize = 25;
+const MAX_STEPS_Q_LAMBDA: usize = 25;
+const MAX_STEPS_N_STEP_SARSA: usize = 25;
+const MAX_STEPS_OFF_MC: usize = 100;
+const STEP_PENALTY_OFF_MC: f32 = 0.1;
+
 const ALPHA_DQN: f32 = 0.1;
 const EPSILON_DQN: f32 = 0.1;
 const GAMMA_DQN: f32 = 0.99;
 const MEMORY_CAPACITY_DQN: usize = 1000;
 const BATCH_SIZE_DQN: usize = 32;
-
-impl TrainedAI {
-    pub fn save(&self, env_name: &str, algorithm_name: &str) -> std::io::Result<()> {
-        // Create models directory if it doesn't exist
-        fs::create_dir_all("models")?;
-
-        let filename = format!("models/{}_{}.json", env_name, algorithm_name);
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(filename.clone(), json)?;
-        println!("Model saved to {}", filename);
-        Ok(())
-    }
-
-    pub fn load(env_name: &str, algorithm_name: &str) -> std::io::Result<Option<Self>> {
-        let filename = format!("models/{}_{}.json", env_name, algorithm_name);
-
-        if Path::new(&filename).exists() {
-            let json = fs::read_to_string(filename.clone())?;
-            let model = serde_json::from_str(&json)?;
-            println!("Model loaded from {}", filename);
-            Ok(Some(model))
-        } else {
-            println!("No saved model found at {}", filename);
-            Ok(None)
-        }
-    }
-    fn get_best_action(&self, state: usize, available_actions: &[usize]) -> usize {
-        match self {
-            TrainedAI::QLearning(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::DynaQ(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::PolicyIteration(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::ValueIteration(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::MonteCarloControl(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::OffPolicyMonteCarloControl(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::Sarsa(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::Reinforce(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::SemiGradientSarsa(ai) => ai.get_best_action(state, available_actions),
-            TrainedAI::DQN(ai) => ai.get_best_action(state, available_actions),
-        }
-    }
-
+const LAMBDA_SARSA_LAMBDA: f32 = 0.9;
+const ALPHA_SARSA_LAMBDA: f32 = 0.1;
+const EPSILON_SARSA_LAMBDA: f32 = 0.1;
+const GAMMA_SARSA_LAMBDA: f32 = 0.99;
+const TRACE_SARSA_LAMBDA: TraceKind = TraceKind::Replacing;
+const LAMBDA_Q_LAMBDA: f32 = 0.9;
+const ALPHA_Q_LAMBDA: f32 = 0.1;
+const EPSILON_Q_LAMBDA: f32 = 0.1;
+const GAMMA_Q_LAMBDA: f32 = 0.99;
+const TRACE_Q_LAMBDA: TraceKind = TraceKind::Replacing;
+const N_N_STEP_SARSA: usize = 4;
+const ALPHA_N_STEP_SARSA: f32 = 0.1;
+const EPSILON_N_STEP_SARSA: f32 = 0.1;
+const GAMMA_N_STEP_SARSA: f32 = 0.99;
+const BACKUP_N_STEP_SARSA: NStepBackup = NStepBackup::Sarsa;
+const SWEEP_N_STEP_SARSA: [usize; 5] = [1, 2, 4, 8, 16];
+const SWEEP_EPISODES_N_STEP_SARSA: usize = 50;
+const SWEEP_STEP_PENALTY_N_STEP_SARSA: f32 = 0.01;
+
+// Off-policy Monte Carlo needs short episodes to keep its importance weights usable
+fn off_mc_env<T: Environment>(env: T) -> StepPenalty<TimeLimit<T>> {
+    StepPenalty::with_penalty(TimeLimit::with_max_steps(env, MAX_STEPS_OFF_MC), STEP_PENALTY_OFF_MC)
 }
 
 fn train_ai(algorithm: &str) -> TrainedAI {
@@ -125,8 +116,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;  // Log every 1000 episodes
 
             println!("Training Q-Learning for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::QLearning(ai)
         },
@@ -144,8 +135,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training Dyna-Q for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::DynaQ(ai)
         },
@@ -161,8 +152,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training PolicyIteration for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::PolicyIteration(ai)
         },
@@ -177,8 +168,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training ValueIteration for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::ValueIteration(ai)
         },
@@ -193,8 +184,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training on policy MonteCarloControl for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::MonteCarloControl(ai)
         },
@@ -209,8 +200,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training OffPolicyMonteCarloControl for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut off_mc_env(env.fresh()), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::OffPolicyMonteCarloControl(ai)
         },
@@ -226,8 +217,8 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training Sarsa for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_SARSA), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::Sarsa(ai)
         },"reinforce" => {
@@ -241,13 +232,14 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training REINFORCE for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::Reinforce(ai)
         },"SemiGradientSarsa" => {
             let mut ai = SemiGradientSarsa::new(
-                env.num_states() * env.num_actions(),
+                env.num_states(),
+                env.num_actions(),
                 ALPHA_SEMI_GRADIENT_SARSA,
                 EPSILON_SEMI_GRADIENT_SARSA,
                 GAMMA_SEMI_GRADIENT_SARSA,
@@ -256,8 +248,11 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training SemiGradientSarsa for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(
+                &mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_SEMI_GRADIENT_SARSA),
+                num_episodes,
+            );
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::SemiGradientSarsa(ai)
         },"DQN" => {
@@ -274,14 +269,76 @@ fn train_ai(algorithm: &str) -> TrainedAI {
             let log_interval = 1000;
 
             println!("Training DQN for {} episodes...", num_episodes);
-            let rewards = ai.train(&mut env.clone(), num_episodes);
-            display_training_stats(&rewards, num_episodes, log_interval);
+            let rewards = ai.train(&mut env.fresh(), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
 
             TrainedAI::DQN(ai)
+        },"SarsaLambda" => {
+            let mut ai = SarsaLambda::new(
+                env.num_states(),
+                env.num_actions(),
+                LAMBDA_SARSA_LAMBDA,
+                ALPHA_SARSA_LAMBDA,
+                EPSILON_SARSA_LAMBDA,
+                GAMMA_SARSA_LAMBDA,
+                TRACE_SARSA_LAMBDA,
+            );
+            let num_episodes = 10000;
+            let log_interval = 1000;
+
+            println!("Training SarsaLambda for {} episodes...", num_episodes);
+            let rewards = ai.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_SARSA_LAMBDA), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
+
+            TrainedAI::SarsaLambda(ai)
+        },"QLambda" => {
+            let mut ai = QLambda::new(
+                env.num_states(),
+                env.num_actions(),
+                LAMBDA_Q_LAMBDA,
+                ALPHA_Q_LAMBDA,
+                EPSILON_Q_LAMBDA,
+                GAMMA_Q_LAMBDA,
+                TRACE_Q_LAMBDA,
+            );
+            let num_episodes = 10000;
+            let log_interval = 1000;
+
+            println!("Training QLambda for {} episodes...", num_episodes);
+            let rewards = ai.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_Q_LAMBDA), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
+
+            TrainedAI::QLambda(ai)
+        },"NStepSarsa" => {
+            let mut ai = NStepSarsa::new(
+                env.num_states(),
+                env.num_actions(),
+                N_N_STEP_SARSA,
+                ALPHA_N_STEP_SARSA,
+                EPSILON_N_STEP_SARSA,
+                GAMMA_N_STEP_SARSA,
+                BACKUP_N_STEP_SARSA,
+            );
+            let num_episodes = 10000;
+            let log_interval = 1000;
+
+            println!("Training NStepSarsa for {} episodes...", num_episodes);
+            let rewards = ai.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_N_STEP_SARSA), num_episodes);
+            report_training(rewards, num_episodes, log_interval);
+
+            TrainedAI::NStepSarsa(ai)
         },
         _ => panic!("Unknown algorithm: {}", algorithm),
     }
 }
+
+fn report_training(result: Result<Vec<f32>, EnvError>, num_episodes: usize, log_interval: usize) {
+    match result {
+        Ok(rewards) => display_training_stats(&rewards, num_episodes, log_interval),
+        Err(e) => println!("Training stopped early: {}", e),
+    }
+}
+
 fn display_training_stats(rewards: &[f32], num_episodes: usize, log_interval: usize) {
     for i in (0..num_episodes).step_by(log_interval) {
         let end = (i + log_interval).min(num_episodes);
@@ -327,9 +384,11 @@ fn play_against_ai(algorithm: &str, env_name: &str) {
 
     while !game.is_game_over() {
         game.display();
-        let state = game.state_id();
-        let ai_action = ai.get_best_action(state, &game.available_actions());
-        game.step(ai_action);
+        let ai_action = ai.best_action_in(&game);
+        if let Err(e) = game.step(ai_action) {
+            println!("{}", e);
+            break;
+        }
     }
 
     println!("\nGame Over!");
@@ -341,6 +400,30 @@ fn play_against_ai(algorithm: &str, env_name: &str) {
     }
 }
 
+fn play_tic_tac_toe(algorithm: &str) {
+    let ai = load_or_train("Tic-Tac-Toe", &TicTacToe::new(), algorithm);
+    let mut game = TicTacToe::new_with_mode(true);
+
+    println!("\nWelcome to Tic-Tac-Toe vs AI ({})!", algorithm);
+    println!("The AI plays X and moves first, you play O.");
+
+    while !game.is_game_over() {
+        let ai_action = ai.best_action_in(&game);
+        if let Err(e) = game.step(ai_action) {
+            println!("{}", e);
+            break;
+        }
+    }
+
+    println!("\nGame Over!");
+    game.display();
+    match game.score() {
+        s if s > 0.0 => println!("AI won the game!"),
+        s if s < 0.0 => println!("You won the game!"),
+        _ => println!("It's a tie!"),
+    }
+}
+
 fn get_user_choice(prompt: &str, options: &[&str]) -> usize {
     loop {
         println!("\n{}", prompt);
@@ -363,10 +446,8 @@ fn get_user_choice(prompt: &str, options: &[&str]) -> usize {
     }
 }
 
-fn run_demonstration<T: Environment + Clone>(env_name: &str, mut env: T, algorithm: &str) {
-    println!("\nDemonstrating {} with {}:", env_name, algorithm);
-
-    let mut ai = if let Ok(Some(loaded_ai)) = TrainedAI::load(env_name, algorithm) {
+fn load_or_train<T: Environment>(env_name: &str, env: &T, algorithm: &str) -> TrainedAI {
+    if let Ok(Some(loaded_ai)) = TrainedAI::load(env_name, algorithm) {
         println!("Using saved model...");
         loaded_ai
     } else {
@@ -425,7 +506,8 @@ fn run_demonstration<T: Environment + Clone>(env_name: &str, mut env: T, algorit
                 GAMMA_REINFORCE,
             )),
             "SemiGradientSarsa" => TrainedAI::SemiGradientSarsa(SemiGradientSarsa::new(
-                env.num_states() * env.num_actions(),
+                env.num_states(),
+                env.num_actions(),
                 ALPHA_SEMI_GRADIENT_SARSA,
                 EPSILON_SEMI_GRADIENT_SARSA,
                 GAMMA_SEMI_GRADIENT_SARSA,
@@ -439,32 +521,80 @@ fn run_demonstration<T: Environment + Clone>(env_name: &str, mut env: T, algorit
                 MEMORY_CAPACITY_DQN,
                 BATCH_SIZE_DQN
             )),
+            "SarsaLambda" => TrainedAI::SarsaLambda(SarsaLambda::new(
+                env.num_states(),
+                env.num_actions(),
+                LAMBDA_SARSA_LAMBDA,
+                ALPHA_SARSA_LAMBDA,
+                EPSILON_SARSA_LAMBDA,
+                GAMMA_SARSA_LAMBDA,
+                TRACE_SARSA_LAMBDA,
+            )),
+            "QLambda" => TrainedAI::QLambda(QLambda::new(
+                env.num_states(),
+                env.num_actions(),
+                LAMBDA_Q_LAMBDA,
+                ALPHA_Q_LAMBDA,
+                EPSILON_Q_LAMBDA,
+                GAMMA_Q_LAMBDA,
+                TRACE_Q_LAMBDA,
+            )),
+            "NStepSarsa" => TrainedAI::NStepSarsa(NStepSarsa::new(
+                env.num_states(),
+                env.num_actions(),
+                N_N_STEP_SARSA,
+                ALPHA_N_STEP_SARSA,
+                EPSILON_N_STEP_SARSA,
+                GAMMA_N_STEP_SARSA,
+                BACKUP_N_STEP_SARSA,
+            )),
             _ => panic!("Unknown algorithm"),
         };
 
         // Train the AI
         println!("\nTraining AI...");
         let rewards = match &mut ai {
-            TrainedAI::QLearning(q) => q.train(&mut env.clone(), 10000),
-            TrainedAI::DynaQ(d) => d.train(&mut env.clone(), 10000),
-            TrainedAI::PolicyIteration(p) => p.train(&mut env.clone(), 10000),
-            TrainedAI::ValueIteration(v) => v.train(&mut env.clone(), 10000),
-            TrainedAI::MonteCarloControl(c) => c.train(&mut env.clone(), 10000),
-            TrainedAI::OffPolicyMonteCarloControl(c) => c.train(&mut env.clone(), 10000),
-            TrainedAI::Sarsa(s) => s.train(&mut env.clone(), 10000),
-            TrainedAI::Reinforce(r) => r.train(&mut env.clone(), 10000),
-            TrainedAI::SemiGradientSarsa(s) => s.train(&mut env.clone(), 10000),
-            TrainedAI::DQN(d) => d.train(&mut env.clone(), 10000),
+            TrainedAI::QLearning(q) => q.train(&mut env.fresh(), 10000),
+            TrainedAI::DynaQ(d) => d.train(&mut env.fresh(), 10000),
+            TrainedAI::PolicyIteration(p) => p.train(&mut env.fresh(), 10000),
+            TrainedAI::ValueIteration(v) => v.train(&mut env.fresh(), 10000),
+            TrainedAI::MonteCarloControl(c) => c.train(&mut env.fresh(), 10000),
+            TrainedAI::OffPolicyMonteCarloControl(c) => c.train(&mut off_mc_env(env.fresh()), 10000),
+            TrainedAI::Sarsa(s) => s.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_SARSA), 10000),
+            TrainedAI::Reinforce(r) => r.train(&mut env.fresh(), 10000),
+            TrainedAI::SemiGradientSarsa(s) => {
+                s.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_SEMI_GRADIENT_SARSA), 10000)
+            }
+            TrainedAI::DQN(d) => d.train(&mut env.fresh(), 10000),
+            TrainedAI::SarsaLambda(s) => {
+                s.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_SARSA_LAMBDA), 10000)
+            }
+            TrainedAI::QLambda(q) => q.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_Q_LAMBDA), 10000),
+            TrainedAI::NStepSarsa(s) => {
+                s.train(&mut TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_N_STEP_SARSA), 10000)
+            }
         };
-        display_training_stats(&rewards, 10000, 1000);
-
-        // Save the trained model
-        if let Err(e) = ai.save(env_name, algorithm) {
-            println!("Warning: Failed to save model: {}", e);
+        match rewards {
+            Ok(rewards) => {
+                display_training_stats(&rewards, 10000, 1000);
+
+                // Save the trained model
+                if let Err(e) = ai.save(env_name, algorithm) {
+                    println!("Warning: Failed to save model: {}", e);
+                }
+            }
+            // Keep the partially trained agent for this run, but do not save it
+            Err(e) => println!("Training stopped early: {}", e),
         }
 
         ai
-    };
+    }
+}
+
+fn run_demonstration<T: Environment>(env_name: &str, mut env: T, algorithm: &str) {
+    println!("\nDemonstrating {} with {}:", env_name, algorithm);
+
+    let ai = load_or_train(env_name, &env, algorithm);
 
     // Demonstrate trained behavior
     println!("\nDemonstrating trained behavior:");
@@ -477,16 +607,83 @@ fn run_demonstration<T: Environment + Clone>(env_name: &str, mut env: T, algorit
 
     while !env.is_game_over() {
         io::stdin().read_line(&mut input).unwrap();
-        let state = env.state_id();
-        let action = ai.get_best_action(state, &env.available_actions());
-        env.step(action);
+        let action = ai.best_action_in(&env);
+        if let Err(e) = env.step(action) {
+            println!("{}", e);
+            break;
+        }
         env.display();
     }
 
     println!("Final score: {}", env.score());
 }
 
+// Trains every n-step backup for a few n and prints the mean return over the
+// first episodes, where the choice of n matters most. The small step penalty
+// makes the return also tell how fast each agent finds the goal.
+fn sweep_n_step_sarsa<T: Environment>(env_name: &str, env: &T) {
+    println!("\nMean return of n-step Sarsa on {} over {} episodes:", env_name, SWEEP_EPISODES_N_STEP_SARSA);
+    for backup in [NStepBackup::Sarsa, NStepBackup::ExpectedSarsa, NStepBackup::TreeBackup] {
+        let mut line = format!("{:>14}", format!("{:?}", backup));
+        for n in SWEEP_N_STEP_SARSA {
+            let mut ai = NStepSarsa::new(
+                env.num_states(),
+                env.num_actions(),
+                n,
+                ALPHA_N_STEP_SARSA,
+                EPSILON_N_STEP_SARSA,
+                GAMMA_N_STEP_SARSA,
+                backup,
+            );
+            let mut train_env = StepPenalty::with_penalty(
+                TimeLimit::with_max_steps(env.fresh(), MAX_STEPS_N_STEP_SARSA),
+                SWEEP_STEP_PENALTY_N_STEP_SARSA,
+            );
+            match ai.train(&mut train_env, SWEEP_EPISODES_N_STEP_SARSA) {
+                Ok(rewards) => {
+                    let mean = rewards.iter().sum::<f32>() / rewards.len() as f32;
+                    line += &format!("  n={:<2} {:>6.3}", n, mean);
+                }
+                Err(e) => line += &format!("  n={:<2} {}", n, e),
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+// Runs a secret environment in this process so a crash cannot take the trainer down
+fn serve_secret_env(env_id: &str) {
+    let Ok(env_id) = env_id.parse::<usize>() else {
+        println!("Usage: serve <secret environment id>");
+        return;
+    };
+    let env = match SecretEnv::from_default_library(env_id) {
+        Ok(env) => env,
+        Err(e) => {
+            println!("Could not load Secret Environment {}: {}", env_id, e);
+            return;
+        }
+    };
+    let listener = match std::net::TcpListener::bind(REMOTE_ENV_ADDR) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("Could not listen on {}: {}", REMOTE_ENV_ADDR, e);
+            return;
+        }
+    };
+    println!("Serving Secret Environment {} on {}", env_id, REMOTE_ENV_ADDR);
+    if let Err(e) = EnvServer::new(env).serve_tcp(listener) {
+        println!("Server stopped: {}", e);
+    }
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "serve" {
+        serve_secret_env(&args[2]);
+        return;
+    }
+
     // Choose algorithm
     let algorithms = ["Q-Learning",
         "Dyna-Q",
@@ -497,7 +694,10 @@ fn main() {
         "Sarsa",
         "Reinforce",
         "SemiGradientSarsa",
-        "DQN"];
+        "DQN",
+        "SarsaLambda",
+        "QLambda",
+        "NStepSarsa"];
     let algorithm = algorithms[get_user_choice(
         "Choose an algorithm:",
         &algorithms
@@ -514,10 +714,29 @@ fn main() {
         "Secret Environment 3",
         "Monty hall 1",
         "Monty hall 2",
-
+        "Blackjack",
+        "Tic-Tac-Toe",
+        "Tic-Tac-Toe (Play against AI)",
+        "Connect Four (random opponent)",
+        "Connect Four (minimax opponent)",
+        "Taxi",
+        "Iterated Rock Paper Scissors (vs win-stay-lose-shift)",
+        "Monty hall N (5 doors, 2 switch stages)",
+        "Monty hall 1 (belief state)",
+        "Remote environment (started with `serve <secret environment id>`)",
+        "Mountain Car",
+        "CartPole",
     ];
     let env_choice = get_user_choice("Choose an environment:", &environments);
 
+    if algorithm == "NStepSarsa" {
+        match env_choice {
+            0 => sweep_n_step_sarsa("Line World", &LineWorld::new()),
+            1 => sweep_n_step_sarsa("Grid World", &GridWorld::new()),
+            _ => {}
+        }
+    }
+
     match env_choice {
         0 => run_demonstration("Line World", LineWorld::new(), algorithm),
         1 => run_demonstration("Grid World", GridWorld::new(), algorithm),
@@ -527,14 +746,47 @@ fn main() {
         },
         3..=6 => {
             let env_id = env_choice - 3;
-            run_demonstration(
-                &format!("Secret Environment {}", env_id),
-                SecretEnv::new(env_id),
-                algorithm
-            );
+            match SecretEnv::from_default_library(env_id) {
+                Ok(env) => run_demonstration(&format!("Secret Environment {}", env_id), env, algorithm),
+                Err(e) => println!("Could not load Secret Environment {}: {}", env_id, e),
+            }
         },
         7 => run_demonstration("Monty hall 1", MontyHall::new(), algorithm),
         8 => run_demonstration("Monty hall 2", MontyHall2::new(), algorithm),
+        9 => run_demonstration("Blackjack", Blackjack::new(), algorithm),
+        10 => run_demonstration("Tic-Tac-Toe", TicTacToe::new(), algorithm),
+        11 => {
+            println!("\nStarting Tic-Tac-Toe against trained {}...", algorithm);
+            play_tic_tac_toe(algorithm);
+        },
+        12 => run_demonstration("Connect Four", ConnectFour::new(), algorithm),
+        13 => run_demonstration(
+            "Connect Four Minimax",
+            ConnectFour::new_with_opponent(ConnectFourOpponent::Minimax { depth: 4 }),
+            algorithm
+        ),
+        14 => run_demonstration("Taxi", Taxi::new(), algorithm),
+        15 => run_demonstration(
+            "Iterated RPS",
+            RPS::iterated(20, 2, Box::new(WinStayLoseShift)),
+            algorithm
+        ),
+        16 => run_demonstration("Monty hall N", MontyHallN::with_config(5, 1, 2), algorithm),
+        17 => run_demonstration(
+            "Monty hall 1 belief",
+            BeliefState::with_resolution(MontyHall::new_with_visibility(Visibility::ObservationOnly), 6),
+            algorithm
+        ),
+        18 => match RemoteEnv::connect_tcp(REMOTE_ENV_ADDR) {
+            Ok(env) => {
+                // Different servers expose different environments, so models are keyed by their size
+                let name = format!("Remote environment {}x{}", env.num_states(), env.num_actions());
+                run_demonstration(&name, env, algorithm)
+            }
+            Err(e) => println!("Could not reach the environment server on {}: {}", REMOTE_ENV_ADDR, e),
+        },
+        19 => run_demonstration("Mountain Car", MountainCar::new(), algorithm),
+        20 => run_demonstration("CartPole", CartPole::new(), algorithm),
         _ => unreachable!(),
     }
 }
\ No newline at end of file
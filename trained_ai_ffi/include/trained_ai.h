@@ -0,0 +1,42 @@
+#ifndef TRAINED_AI_H
+#define TRAINED_AI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TRAINED_AI_ABI_VERSION 1
+
+/* Returned by trained_ai_best_action when the mask has no available action. */
+#define TRAINED_AI_NO_ACTION (-1)
+/* Returned by trained_ai_best_action on a null argument, an unknown state or
+ * a mask longer than the number of actions of the model. Models trained on
+ * observations (DQN and semi-gradient Sarsa with non one-hot features) choose
+ * from the observation, not the state id, and saved models keep no
+ * observation: through this API they return TRAINED_AI_ERROR for every
+ * state. */
+#define TRAINED_AI_ERROR (-2)
+
+typedef struct TrainedAI TrainedAI;
+
+uint32_t trained_ai_abi_version(void);
+
+/* Loads a model saved as JSON by the workspace, or returns NULL. */
+TrainedAI* trained_ai_load(const char* path);
+
+/* Best action in `state` among the actions whose byte in `action_mask` is
+ * non-zero; `mask_len` is the number of actions of the environment. */
+int64_t trained_ai_best_action(const TrainedAI* ai, size_t state,
+                               const uint8_t* action_mask, size_t mask_len);
+
+/* Frees a model returned by trained_ai_load. NULL is ignored. */
+void trained_ai_free(TrainedAI* ai);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
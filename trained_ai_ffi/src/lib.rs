@@ -0,0 +1,86 @@
+//! C ABI over the agents trained by this workspace, so game code in other
+//! languages can play a saved policy. `include/trained_ai.h` declares the
+//! functions below.
+//!
+//! A model is loaded from a JSON file written by `TrainedAI::save`, queried
+//! with a state id and a mask of the available actions, and freed once the
+//! caller is done with it. Arguments are checked against the model before
+//! it is queried, so failures return a null pointer or a negative action
+//! instead of panicking across the boundary.
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+use algorithms::trained_ai::TrainedAI;
+
+/// Version of the functions declared in `trained_ai.h`.
+pub const TRAINED_AI_ABI_VERSION: u32 = 1;
+
+/// Returned by `trained_ai_best_action` when the mask has no available action.
+pub const TRAINED_AI_NO_ACTION: i64 = -1;
+/// Returned by `trained_ai_best_action` on a null argument, a state the model
+/// does not know or a mask longer than its number of actions. Models that
+/// learned from observations know no state once loaded, as observations are
+/// not saved.
+pub const TRAINED_AI_ERROR: i64 = -2;
+
+#[no_mangle]
+pub extern "C" fn trained_ai_abi_version() -> u32 {
+    TRAINED_AI_ABI_VERSION
+}
+
+/// Loads a model saved as JSON at `path`, or returns null if it cannot be read.
+///
+/// # Safety
+/// `path` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn trained_ai_load(path: *const c_char) -> *mut TrainedAI {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return ptr::null_mut();
+    };
+    match TrainedAI::from_file(path) {
+        Ok(ai) => Box::into_raw(Box::new(ai)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Best action in `state` among the actions whose byte in `action_mask` is
+/// non-zero. `mask_len` is the number of actions of the environment.
+///
+/// # Safety
+/// `ai` must be null or come from `trained_ai_load`, and `action_mask` must be
+/// null or point to `mask_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn trained_ai_best_action(
+    ai: *const TrainedAI,
+    state: usize,
+    action_mask: *const u8,
+    mask_len: usize,
+) -> i64 {
+    if ai.is_null() || (action_mask.is_null() && mask_len > 0) {
+        return TRAINED_AI_ERROR;
+    }
+    let ai = &*ai;
+    if !ai.knows_state(state) || mask_len > ai.num_actions() {
+        return TRAINED_AI_ERROR;
+    }
+    let mask = if mask_len == 0 { &[][..] } else { std::slice::from_raw_parts(action_mask, mask_len) };
+    let available_actions: Vec<usize> = (0..mask_len).filter(|&a| mask[a] != 0).collect();
+    if available_actions.is_empty() {
+        return TRAINED_AI_NO_ACTION;
+    }
+    ai.get_best_action(state, &available_actions) as i64
+}
+
+/// Frees a model returned by `trained_ai_load`. Null is ignored.
+///
+/// # Safety
+/// `ai` must be null or come from `trained_ai_load`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn trained_ai_free(ai: *mut TrainedAI) {
+    if !ai.is_null() {
+        drop(Box::from_raw(ai));
+    }
+}
@@ -0,0 +1,86 @@
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::ffi::{c_char, c_void, CString};
+use std::path::PathBuf;
+use libloading::Library;
+use algorithms::RLAlgorithm;
+use algorithms::dqn::DQN;
+use algorithms::features::FeatureExtractor;
+use algorithms::trained_ai::TrainedAI;
+use environments::Environment;
+use environments::line_world::LineWorld;
+
+const MODEL: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../models/Line World_Q-Learning.json");
+
+type Load = unsafe extern "C" fn(*const c_char) -> *mut c_void;
+type BestAction = unsafe extern "C" fn(*const c_void, usize, *const u8, usize) -> i64;
+type Free = unsafe extern "C" fn(*mut c_void);
+
+// Cargo builds the cdylib next to the test binary's directory, in target/<profile>
+fn library() -> Library {
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let name = format!("{}trained_ai_ffi{}", DLL_PREFIX, DLL_SUFFIX);
+    let path: PathBuf = [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("the trained_ai_ffi cdylib was not built");
+    unsafe { Library::new(path).unwrap() }
+}
+
+#[test]
+fn test_saved_line_world_agent_through_the_c_abi() {
+    let lib = library();
+    unsafe {
+        let version = lib.get::<unsafe extern "C" fn() -> u32>(b"trained_ai_abi_version").unwrap();
+        let load = lib.get::<Load>(b"trained_ai_load").unwrap();
+        let best_action = lib.get::<BestAction>(b"trained_ai_best_action").unwrap();
+        let free = lib.get::<Free>(b"trained_ai_free").unwrap();
+        assert_eq!(version(), 1);
+
+        let path = CString::new(MODEL).unwrap();
+        let ai = load(path.as_ptr());
+        assert!(!ai.is_null());
+
+        // The saved agent walks right from the middle of the line
+        assert_eq!(best_action(ai, 2, [1, 1].as_ptr(), 2), 1);
+        // Only the masked actions are considered
+        assert_eq!(best_action(ai, 2, [1, 0].as_ptr(), 2), 0);
+        assert_eq!(best_action(ai, 2, [0, 0].as_ptr(), 2), -1);
+        // Unknown states and actions are reported instead of indexing out of the model
+        assert_eq!(best_action(ai, 99, [1, 1].as_ptr(), 2), -2);
+        assert_eq!(best_action(ai, 2, [0, 0, 1].as_ptr(), 3), -2);
+        assert_eq!(best_action(std::ptr::null(), 2, [1, 1].as_ptr(), 2), -2);
+
+        free(ai);
+        free(std::ptr::null_mut());
+
+        let missing = CString::new("./missing/model.json").unwrap();
+        assert!(load(missing.as_ptr()).is_null());
+    }
+}
+
+#[test]
+fn test_observation_models_are_reported_as_errors() {
+    let mut env = LineWorld::new();
+    let extractor = FeatureExtractor::Polynomial { dims: 1, degree: 2 };
+    let mut dqn = DQN::with_features(extractor, env.num_actions(), 0.01, 0.2, 0.9, 1000, 16);
+    dqn.train(&mut env, 20).unwrap();
+    let path = std::env::temp_dir().join(format!("c_abi_dqn_{}.json", std::process::id()));
+    TrainedAI::DQN(dqn).to_file(&path).unwrap();
+
+    let lib = library();
+    unsafe {
+        let load = lib.get::<Load>(b"trained_ai_load").unwrap();
+        let best_action = lib.get::<BestAction>(b"trained_ai_best_action").unwrap();
+        let free = lib.get::<Free>(b"trained_ai_free").unwrap();
+
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let ai = load(c_path.as_ptr());
+        assert!(!ai.is_null());
+        // The observations are not saved, so even the states it trained in are unknown
+        assert_eq!(best_action(ai, 2, [1, 1].as_ptr(), 2), -2);
+        assert_eq!(best_action(ai, 5, [1, 1].as_ptr(), 2), -2);
+        free(ai);
+    }
+    std::fs::remove_file(&path).unwrap();
+}